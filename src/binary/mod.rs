@@ -0,0 +1,357 @@
+use std::fmt;
+
+use deku::prelude::*;
+use num_enum::TryFromPrimitive;
+
+use crate::bit_board::BitBoardPosition;
+use crate::definitions::{Move, PieceType, PositionError, Square, INITIAL_POSITION};
+use crate::fen::FenError;
+use crate::pgn::{GameResult, PgnGame, PgnMove};
+
+const MAGIC: [u8; 4] = *b"FDCG";
+pub const FORMAT_VERSION: u8 = 1;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum BinaryGameError {
+    /// The bytes don't start with the format's magic number.
+    NotAGame,
+    UnknownVersion(u8),
+    Truncated,
+    Corrupt(String),
+    InvalidFen(FenError),
+    /// The stored FEN reads, but isn't a position a game could be played from.
+    InvalidPosition(Vec<PositionError>),
+    /// `ply` counts half moves from the start of the game, starting at 1.
+    IllegalMove { ply: usize },
+    /// What has more entries or bytes than its 16-bit length can count.
+    TooLarge(String),
+}
+
+impl fmt::Display for BinaryGameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAGame => write!(f, "Not a binary game record"),
+            Self::UnknownVersion(version) => write!(f, "Binary game format version {} isn't supported, only up to {}", version, FORMAT_VERSION),
+            Self::Truncated => write!(f, "Binary game record ends early"),
+            Self::Corrupt(reason) => write!(f, "Corrupt binary game record: {}", reason),
+            Self::InvalidFen(err) => write!(f, "Invalid start position: {}", err),
+            Self::InvalidPosition(errors) => {
+                let reasons: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+                write!(f, "Invalid start position: {}", reasons.join(", "))
+            },
+            Self::IllegalMove { ply } => write!(f, "Move at ply {} isn't legal", ply),
+            Self::TooLarge(what) => write!(f, "Too many {} to store", what),
+        }
+    }
+}
+
+impl std::error::Error for BinaryGameError {}
+
+impl From<DekuError> for BinaryGameError {
+    fn from(err: DekuError) -> Self {
+        match err {
+            DekuError::Incomplete(_) => Self::Truncated,
+            err => Self::Corrupt(err.to_string()),
+        }
+    }
+}
+
+/// A game in the compact binary format. Games are written back to back, so
+/// a file holds as many as it needs to.
+///
+/// Each starts with the magic number `FDCG` and a version byte. Version 1
+/// follows with, little endian throughout:
+/// - the start position, a `0` byte for the initial one or a `1` byte and
+///   its FEN
+/// - the result, `0` for unknown, `1` white wins, `2` black wins, `3` a draw
+/// - a `u16` count of tag pairs, each a name and a value
+/// - a `u16` count of moves, each a `u16` from `encode_move`
+///
+/// Strings are a `u16` byte length followed by UTF-8.
+#[derive(Clone, Debug)]
+pub struct BinaryGame {
+    pub start_position: BitBoardPosition,
+    pub tags: Vec<(String, String)>,
+    pub result: GameResult,
+    pub moves: Vec<Move>,
+}
+
+#[derive(DekuRead, DekuWrite)]
+struct Header {
+    magic: [u8; 4],
+    version: u8,
+}
+
+#[derive(DekuRead, DekuWrite)]
+#[deku(endian = "little")]
+struct GameRecordV1 {
+    start_position: StartPosition,
+    result: u8,
+    tag_count: u16,
+    #[deku(count = "tag_count")]
+    tags: Vec<(Text, Text)>,
+    move_count: u16,
+    #[deku(count = "move_count")]
+    moves: Vec<u16>,
+}
+
+#[derive(DekuRead, DekuWrite)]
+#[deku(type = "u8", ctx = "endian: deku::ctx::Endian", endian = "endian")]
+enum StartPosition {
+    #[deku(id = "0")]
+    Initial,
+    #[deku(id = "1")]
+    Fen(Text),
+}
+
+#[derive(DekuRead, DekuWrite)]
+#[deku(ctx = "endian: deku::ctx::Endian", endian = "endian")]
+struct Text {
+    length: u16,
+    #[deku(count = "length")]
+    bytes: Vec<u8>,
+}
+
+impl Text {
+    fn new(text: &str) -> Result<Self, BinaryGameError> {
+        let length = u16::try_from(text.len())
+            .map_err(|_| BinaryGameError::TooLarge("bytes in a string".to_string()))?;
+        Ok(Text { length, bytes: text.as_bytes().to_vec() })
+    }
+
+    fn into_string(self) -> Result<String, BinaryGameError> {
+        String::from_utf8(self.bytes).map_err(|_| BinaryGameError::Corrupt("string isn't UTF-8".to_string()))
+    }
+}
+
+impl BinaryGame {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, BinaryGameError> {
+        let start_fen = self.start_position.to_fen();
+        let start_position = if start_fen == INITIAL_POSITION.to_fen() {
+            StartPosition::Initial
+        } else {
+            StartPosition::Fen(Text::new(&start_fen)?)
+        };
+
+        let tags = self.tags.iter()
+            .map(|(name, value)| Ok((Text::new(name)?, Text::new(value)?)))
+            .collect::<Result<Vec<_>, BinaryGameError>>()?;
+
+        let record = GameRecordV1 {
+            start_position,
+            result: match self.result {
+                GameResult::Unknown => 0,
+                GameResult::WhiteWins => 1,
+                GameResult::BlackWins => 2,
+                GameResult::Draw => 3,
+            },
+            tag_count: u16::try_from(tags.len()).map_err(|_| BinaryGameError::TooLarge("tags".to_string()))?,
+            tags,
+            move_count: u16::try_from(self.moves.len()).map_err(|_| BinaryGameError::TooLarge("moves".to_string()))?,
+            moves: self.moves.iter().map(|played_move| encode_move(*played_move)).collect(),
+        };
+
+        let mut bytes = Header { magic: MAGIC, version: FORMAT_VERSION }.to_bytes()?;
+        bytes.extend(record.to_bytes()?);
+        Ok(bytes)
+    }
+
+    /// Reads the game at the start of `bytes` and returns it with the bytes
+    /// that follow it. The moves are replayed to check they are legal.
+    pub fn from_bytes(bytes: &[u8]) -> Result<(Self, &[u8]), BinaryGameError> {
+        // too few bytes only means the record is cut short if they could be
+        // the start of the magic number
+        let magic_length = bytes.len().min(MAGIC.len());
+        if bytes[..magic_length] != MAGIC[..magic_length] {
+            return Err(BinaryGameError::NotAGame);
+        }
+        let ((rest, _), header) = Header::from_bytes((bytes, 0))?;
+        if header.version != FORMAT_VERSION {
+            return Err(BinaryGameError::UnknownVersion(header.version));
+        }
+
+        let ((rest, _), record) = GameRecordV1::from_bytes((rest, 0))?;
+
+        let start_position = match record.start_position {
+            StartPosition::Initial => BitBoardPosition::from_position(&INITIAL_POSITION),
+            StartPosition::Fen(fen) => BitBoardPosition::from_fen(&fen.into_string()?).map_err(BinaryGameError::InvalidFen)?,
+        };
+        start_position.validate().map_err(BinaryGameError::InvalidPosition)?;
+
+        let result = match record.result {
+            0 => GameResult::Unknown,
+            1 => GameResult::WhiteWins,
+            2 => GameResult::BlackWins,
+            3 => GameResult::Draw,
+            other => return Err(BinaryGameError::Corrupt(format!("{} isn't a game result", other))),
+        };
+
+        let tags = record.tags.into_iter()
+            .map(|(name, value)| Ok((name.into_string()?, value.into_string()?)))
+            .collect::<Result<Vec<_>, BinaryGameError>>()?;
+
+        let mut position = start_position.clone();
+        let mut moves = vec![];
+        for (index, encoded) in record.moves.into_iter().enumerate() {
+            let illegal = BinaryGameError::IllegalMove { ply: index + 1 };
+            let played_move = decode_move(encoded, &position).ok_or(illegal.clone())?;
+            position.make_move((played_move.start, played_move.end), played_move.promotion).map_err(|_| illegal)?;
+            moves.push(played_move);
+        }
+
+        Ok((BinaryGame { start_position, tags, result, moves }, rest))
+    }
+}
+
+/// Packs the start square into bits 0-5, the end square into bits 6-11 and
+/// the promotion piece type, or 0 for none, into bits 12-14. The moving
+/// piece isn't stored, it comes from the position when decoding.
+pub fn encode_move(played_move: Move) -> u16 {
+    let promotion = played_move.promotion.map_or(0, |piece_type| piece_type as u16);
+    played_move.start as u16 | (played_move.end as u16) << 6 | promotion << 12
+}
+
+/// The legal move in `position` that `encode_move` packed into `encoded`.
+pub fn decode_move(encoded: u16, position: &BitBoardPosition) -> Option<Move> {
+    let start = Square::try_from_primitive((encoded & 0x3f) as u8).ok()?;
+    let end = Square::try_from_primitive((encoded >> 6 & 0x3f) as u8).ok()?;
+    let promotion = match encoded >> 12 {
+        0 => None,
+        piece_type => Some(PieceType::try_from_primitive(piece_type as u8).ok()?),
+    };
+    position.legal_moves()
+        .into_iter()
+        .find(|legal_move| legal_move.start == start && legal_move.end == end && legal_move.promotion == promotion)
+}
+
+// keeps the tags, result and moves, annotations aren't stored
+impl From<&PgnGame> for BinaryGame {
+    fn from(game: &PgnGame) -> Self {
+        BinaryGame {
+            start_position: game.start_position.clone(),
+            tags: game.tags.clone(),
+            result: game.result,
+            moves: game.moves.iter().map(|pgn_move| pgn_move.played_move).collect(),
+        }
+    }
+}
+
+impl From<BinaryGame> for PgnGame {
+    fn from(game: BinaryGame) -> Self {
+        let mut pgn_game = PgnGame::new(game.start_position);
+        pgn_game.tags = game.tags;
+        pgn_game.result = game.result;
+        pgn_game.moves = game.moves.into_iter()
+            .map(|played_move| PgnMove { played_move, comment: None, nags: vec![], clock: None, variations: vec![] })
+            .collect();
+        pgn_game
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{decode_move, encode_move, BinaryGame, BinaryGameError, FORMAT_VERSION};
+    use crate::bit_board::BitBoardPosition;
+    use crate::definitions::{Move, Piece, PieceType, Player, PositionError, Square, INITIAL_POSITION};
+    use crate::pgn::{parse_game, GameResult};
+
+    fn game(pgn: &str) -> BinaryGame {
+        BinaryGame::from(&parse_game(pgn).unwrap())
+    }
+
+    fn encoded(pgn: &str) -> Vec<u8> {
+        game(pgn).to_bytes().unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let original = game("[White \"Ünïcode\"]\n[FEN \"4k3/P7/8/8/8/8/8/4K3 w - - 0 1\"]\n1. a8=N Kd7 2. Nb6+ 1-0");
+        let mut bytes = original.to_bytes().unwrap();
+        let second = encoded("1. e4 e5 2. Nf3 *");
+        bytes.extend(&second);
+
+        let (game, rest) = BinaryGame::from_bytes(&bytes).unwrap();
+        assert_eq!(rest, second.as_slice());
+        assert_eq!(game.start_position.to_fen(), original.start_position.to_fen());
+        assert_eq!(game.tags, original.tags);
+        assert_eq!(game.result, GameResult::WhiteWins);
+        assert_eq!(game.moves, original.moves);
+        assert_eq!(game.moves[0].promotion, Some(PieceType::Knight));
+
+        let (game, rest) = BinaryGame::from_bytes(rest).unwrap();
+        assert!(rest.is_empty());
+        assert_eq!(game.start_position.to_fen(), INITIAL_POSITION.to_fen());
+        assert_eq!((game.moves.len(), game.result), (3, GameResult::Unknown));
+    }
+
+    #[test]
+    fn every_truncation() {
+        for pgn in ["[Event \"Truncated\"]\n1. e4 e5 1/2-1/2", "[FEN \"4k3/8/8/8/8/8/8/4K2R w K - 0 1\"]\n1. O-O *"] {
+            let bytes = encoded(pgn);
+            for length in 0..bytes.len() {
+                assert_eq!(BinaryGame::from_bytes(&bytes[..length]).err(), Some(BinaryGameError::Truncated), "{} bytes", length);
+            }
+        }
+    }
+
+    #[test]
+    fn header_errors() {
+        let mut bytes = encoded("1. e4 *");
+        bytes[0] = b'X';
+        assert_eq!(BinaryGame::from_bytes(&bytes).err(), Some(BinaryGameError::NotAGame));
+        assert_eq!(BinaryGame::from_bytes(b"PGN").err(), Some(BinaryGameError::NotAGame));
+
+        let mut bytes = encoded("1. e4 *");
+        bytes[4] = FORMAT_VERSION + 1;
+        assert_eq!(BinaryGame::from_bytes(&bytes).err(), Some(BinaryGameError::UnknownVersion(FORMAT_VERSION + 1)));
+    }
+
+    #[test]
+    fn corrupt_records() {
+        let mut bytes = encoded("1. e4 *");
+        // magic, version, initial position, then the result
+        bytes[6] = 4;
+        assert!(matches!(BinaryGame::from_bytes(&bytes), Err(BinaryGameError::Corrupt(_))));
+
+        let mut bytes = encoded("[FEN \"4k3/8/8/8/8/8/8/4K3 w - - 0 1\"]\n*");
+        let fen_start = 8;
+        bytes[fen_start] = b'x';
+        assert!(matches!(BinaryGame::from_bytes(&bytes), Err(BinaryGameError::InvalidFen(_))));
+
+        let king = Piece { piece_type: PieceType::King, owner: Player::White };
+        let two_kings = BinaryGame {
+            start_position: BitBoardPosition::from_fen("7r/8/8/8/8/8/8/K6K w - - 0 1").unwrap(),
+            tags: vec![],
+            result: GameResult::Unknown,
+            moves: vec![Move { start: Square::A1, end: Square::B1, piece: king, promotion: None }],
+        };
+        assert_eq!(
+            BinaryGame::from_bytes(&two_kings.to_bytes().unwrap()).err(),
+            Some(BinaryGameError::InvalidPosition(vec![PositionError::TooManyKings(Player::White), PositionError::MissingKing(Player::Black)])),
+        );
+    }
+
+    #[test]
+    fn illegal_encoded_moves() {
+        let position = BitBoardPosition::from_position(&INITIAL_POSITION);
+        let moves = game("1. e4 e5 *").moves;
+        let (e4, e5) = (moves[0], moves[1]);
+        assert_eq!(decode_move(encode_move(e4), &position), Some(e4));
+        assert_eq!(decode_move(encode_move(e5), &position), None);
+
+        // the moves are the last four bytes
+        let mut bytes = encoded("1. e4 e5 *");
+        let moves = bytes.len() - 4;
+        bytes[moves..moves + 2].copy_from_slice(&encode_move(e5).to_le_bytes());
+        assert_eq!(BinaryGame::from_bytes(&bytes).err(), Some(BinaryGameError::IllegalMove { ply: 1 }));
+
+        let mut bytes = encoded("1. e4 e5 *");
+        bytes[moves + 2..].copy_from_slice(&encode_move(e4).to_le_bytes());
+        assert_eq!(BinaryGame::from_bytes(&bytes).err(), Some(BinaryGameError::IllegalMove { ply: 2 }));
+
+        // 7 isn't a piece type to promote to
+        let mut bytes = encoded("1. e4 e5 *");
+        bytes[moves..moves + 2].copy_from_slice(&(encode_move(e4) | 7 << 12).to_le_bytes());
+        assert_eq!(BinaryGame::from_bytes(&bytes).err(), Some(BinaryGameError::IllegalMove { ply: 1 }));
+    }
+}
@@ -0,0 +1,152 @@
+use super::{
+    BitBoard,
+    BitBoardPosition,
+    PieceType,
+    Pin,
+    Player,
+    Square,
+    calculations::{self, precalculations::attack_sets},
+};
+
+impl BitBoardPosition {
+    /// Bitboard of the pieces `by_player` attacks `square` with.
+    pub fn attackers_to(&self, square: Square, by_player: Player) -> u64 {
+        calculations::attackers_to(square, &self.board, by_player, self.occupancy())
+    }
+
+    /// Bitboard of every square `player` attacks, pieces of either side
+    /// included.
+    pub fn attacked_squares(&self, player: Player) -> u64 {
+        calculations::attacked_squares(&self.board, player, self.occupancy())
+    }
+
+    /// The opponent's pieces giving check to the player to move.
+    pub fn checkers(&self) -> u64 {
+        match self.king_square(self.to_play) {
+            Some(king) => self.attackers_to(king, self.to_play.opponent()),
+            None => 0,
+        }
+    }
+
+    /// Only a king move gets out of a double check.
+    pub fn is_double_check(&self) -> bool {
+        self.checkers().count_ones() > 1
+    }
+
+    /// The player to move's pieces pinned against their own king.
+    pub fn pins(&self) -> Vec<Pin> {
+        let Some(king) = self.king_square(self.to_play) else { return vec![]; };
+        let opponent = self.to_play.opponent();
+        let layer = |piece_type: PieceType| self.board[piece_type as usize + (6 * opponent as usize)];
+        let all_pieces = self.occupancy();
+        let own_pieces = self.player_occupancy(self.to_play);
+
+        // sliders that would attack the king if nothing stood in between
+        let snipers = attack_sets().attacks(king, PieceType::Rook) & (layer(PieceType::Rook) | layer(PieceType::Queen))
+            | attack_sets().attacks(king, PieceType::Bishop) & (layer(PieceType::Bishop) | layer(PieceType::Queen));
+
+        BitBoard(snipers)
+            .filter_map(|pinner| {
+                let path = attack_sets().between(king, pinner);
+                let blockers = path & all_pieces;
+                if blockers.count_ones() != 1 || blockers & own_pieces == 0 { return None; }
+
+                Some(Pin { pinned: Square::from_u64(blockers), pinner, ray: path | pinner.to_u64() })
+            })
+            .collect()
+    }
+
+    /// Bitboard of every piece in `pins`.
+    pub fn pinned_pieces(&self) -> u64 {
+        self.pins().iter().fold(0, |acc, pin| acc | pin.pinned.to_u64())
+    }
+
+    pub(super) fn king_square(&self, player: Player) -> Option<Square> {
+        BitBoard(self.board[PieceType::King as usize + (6 * player as usize)]).next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitBoardPosition, Pin, Player, Square};
+
+    fn position(fen: &str) -> BitBoardPosition {
+        BitBoardPosition::from_fen(fen).unwrap()
+    }
+
+    fn squares(squares: &[Square]) -> u64 {
+        squares.iter().fold(0, |acc, square| acc | square.to_u64())
+    }
+
+    #[test]
+    fn attackers() {
+        let initial = position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        assert_eq!(initial.attackers_to(Square::F3, Player::White), squares(&[Square::E2, Square::G2, Square::G1]));
+        assert_eq!(initial.attackers_to(Square::E4, Player::White), 0);
+        assert_eq!(initial.attackers_to(Square::F6, Player::White), 0);
+
+        // sliders stop at the first piece in their way, whichever side it belongs to
+        let open = position("4k3/8/8/1b6/8/3N4/8/R3K2R w - - 0 1");
+        assert_eq!(open.attackers_to(Square::E1, Player::Black), 0);
+        assert_eq!(open.attackers_to(Square::D3, Player::Black), squares(&[Square::B5]));
+        assert_eq!(open.attackers_to(Square::F1, Player::White), squares(&[Square::E1, Square::H1]));
+        assert_eq!(open.attackers_to(Square::C5, Player::White), squares(&[Square::D3]));
+    }
+
+    #[test]
+    fn attacked() {
+        let initial = position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        let white = 0x00FF_FF7E;
+        assert_eq!(initial.attacked_squares(Player::White), white);
+        assert_eq!(initial.attacked_squares(Player::Black), white.swap_bytes());
+
+        let lone_rook = position("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+        let rook = 0x0101_0101_0101_011E;
+        let king = squares(&[Square::D1, Square::D2, Square::E2, Square::F2, Square::F1]);
+        assert_eq!(lone_rook.attacked_squares(Player::White), rook | king);
+    }
+
+    #[test]
+    fn checks() {
+        let quiet = position("4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+        assert_eq!(quiet.checkers(), 0);
+        assert!(!quiet.is_double_check());
+
+        let single = position("4k3/8/8/8/1b6/8/8/4K3 w - - 0 1");
+        assert_eq!(single.checkers(), squares(&[Square::B4]));
+        assert!(!single.is_double_check());
+
+        // Ne4-d6+ uncovers the rook on the e-file
+        let discovered = position("4k3/8/3N4/8/8/8/8/4R1K1 b - - 0 1");
+        assert_eq!(discovered.checkers(), squares(&[Square::D6, Square::E1]));
+        assert!(discovered.is_double_check());
+        assert!(discovered.legal_moves().iter().all(|legal_move| legal_move.start == Square::E8));
+    }
+
+    #[test]
+    fn pins() {
+        let file = position("4k3/4r3/8/8/8/8/4B3/4K3 w - - 0 1");
+        let ray = squares(&[Square::E2, Square::E3, Square::E4, Square::E5, Square::E6, Square::E7]);
+        assert_eq!(file.pins(), vec![Pin { pinned: Square::E2, pinner: Square::E7, ray }]);
+        assert_eq!(file.pinned_pieces(), squares(&[Square::E2]));
+
+        let diagonal = position("4k3/8/8/b7/8/8/3P4/4K3 w - - 0 1");
+        let ray = squares(&[Square::D2, Square::C3, Square::B4, Square::A5]);
+        assert_eq!(diagonal.pins(), vec![Pin { pinned: Square::D2, pinner: Square::A5, ray }]);
+
+        // two pieces in the way, or an enemy one, is no pin
+        assert_eq!(position("4k3/4r3/8/8/4N3/8/4B3/4K3 w - - 0 1").pinned_pieces(), 0);
+        assert_eq!(position("4k3/4r3/8/8/8/8/4b3/4K3 w - - 0 1").pinned_pieces(), 0);
+    }
+
+    #[test]
+    fn en_passant_pin() {
+        // bxc6 would take both pawns off the fifth rank at once
+        let pinned = position("8/8/8/KPp4r/8/8/8/7k w - c6 0 1");
+        assert_eq!(pinned.pinned_pieces(), 0);
+        let captures = |position: &BitBoardPosition| position.legal_moves().iter()
+            .any(|legal_move| legal_move.start == Square::B5 && legal_move.end == Square::C6);
+        assert!(!captures(&pinned));
+        assert!(captures(&position("8/8/8/KPp5/8/8/8/7k w - c6 0 1")));
+    }
+}
@@ -0,0 +1,167 @@
+use crate::definitions::{DrawReason, GameStatus};
+
+use super::{
+    BitBoardPosition,
+    PieceType,
+    Player,
+    calculations,
+};
+
+const LIGHT_SQUARES: u64 = 0x55AA_55AA_55AA_55AA;
+
+impl BitBoardPosition {
+    /// `history` holds the Zobrist key of every position reached in the game,
+    /// this one included. Only draws that end the game without a claim are
+    /// reported here, see `claimable_draw` for the others.
+    pub fn status(&self, history: &[u64]) -> GameStatus {
+        if self.legal_moves().is_empty() {
+            if calculations::is_king_in_check(&self.board, self.to_play) {
+                return GameStatus::Checkmate(self.to_play.opponent());
+            }
+            return GameStatus::Stalemate;
+        }
+
+        if self.halfmove_clock >= 150 {
+            return GameStatus::Draw(DrawReason::SeventyFiveMoveRule);
+        }
+
+        if self.repetition_count(history) >= 5 {
+            return GameStatus::Draw(DrawReason::FivefoldRepetition);
+        }
+
+        if self.has_insufficient_material() {
+            return GameStatus::Draw(DrawReason::InsufficientMaterial);
+        }
+
+        GameStatus::Ongoing
+    }
+
+    /// A draw the player to move may claim but is not forced to take.
+    pub fn claimable_draw(&self, history: &[u64]) -> Option<DrawReason> {
+        if self.repetition_count(history) >= 3 {
+            return Some(DrawReason::ThreefoldRepetition);
+        }
+
+        if self.halfmove_clock >= 100 {
+            return Some(DrawReason::FiftyMoveRule);
+        }
+
+        None
+    }
+
+    /// How many times this position's key appears in `history`. Positions
+    /// from before the last capture or pawn move can never repeat, so only
+    /// those covered by the halfmove clock are compared.
+    pub fn repetition_count(&self, history: &[u64]) -> usize {
+        history
+            .iter()
+            .rev()
+            .take(self.halfmove_clock as usize + 1)
+            .filter(|previous| **previous == self.hash)
+            .count()
+    }
+
+    /// Neither side can ever deliver mate: bare kings, a single minor piece,
+    /// or only bishops which all stand on the same colour.
+    pub fn has_insufficient_material(&self) -> bool {
+        let layers = |piece_type: PieceType| {
+            self.board[piece_type as usize + (6 * Player::White as usize)]
+                | self.board[piece_type as usize + (6 * Player::Black as usize)]
+        };
+
+        if layers(PieceType::Pawn) | layers(PieceType::Rook) | layers(PieceType::Queen) != 0 {
+            return false;
+        }
+
+        let knights = layers(PieceType::Knight);
+        let bishops = layers(PieceType::Bishop);
+
+        match (knights.count_ones(), bishops.count_ones()) {
+            (0, _) => bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0,
+            (1, 0) => true,
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BitBoardPosition;
+    use crate::definitions::{DrawReason, GameStatus, Player, Square, INITIAL_POSITION};
+
+    fn position(fen: &str) -> BitBoardPosition {
+        BitBoardPosition::from_fen(fen).unwrap()
+    }
+
+    fn status(fen: &str) -> GameStatus {
+        position(fen).status(&[])
+    }
+
+    #[test]
+    fn mate_and_stalemate() {
+        assert_eq!(status("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3"), GameStatus::Checkmate(Player::Black));
+        assert_eq!(status("6rk/5Npp/8/8/8/8/8/6K1 b - - 0 1"), GameStatus::Checkmate(Player::White));
+        assert_eq!(status("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1"), GameStatus::Stalemate);
+        assert_eq!(status("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), GameStatus::Ongoing);
+    }
+
+    #[test]
+    fn move_rules() {
+        let clock = |halfmove_clock: u16| position(&format!("4k3/8/8/8/8/8/4P3/4K3 w - - {} 80", halfmove_clock));
+
+        assert_eq!(clock(99).claimable_draw(&[]), None);
+        assert_eq!(clock(100).claimable_draw(&[]), Some(DrawReason::FiftyMoveRule));
+        assert_eq!(clock(100).status(&[]), GameStatus::Ongoing);
+        assert_eq!(clock(149).status(&[]), GameStatus::Ongoing);
+        assert_eq!(clock(150).status(&[]), GameStatus::Draw(DrawReason::SeventyFiveMoveRule));
+
+        // mate on the move that reaches the limit still counts
+        assert_eq!(status("6rk/5Npp/8/8/8/8/8/6K1 b - - 150 100"), GameStatus::Checkmate(Player::White));
+    }
+
+    #[test]
+    fn repetitions() {
+        let mut position = BitBoardPosition::from_position(&INITIAL_POSITION);
+        let mut history = vec![position.zobrist_key()];
+        let shuffle = [(Square::G1, Square::F3), (Square::G8, Square::F6), (Square::F3, Square::G1), (Square::F6, Square::G8)];
+
+        for round in 1..=4 {
+            for tentative_move in shuffle {
+                position.make_move(tentative_move, None).unwrap();
+                history.push(position.zobrist_key());
+            }
+            let occurrences = round + 1;
+            assert_eq!(position.repetition_count(&history), occurrences);
+
+            let expected_claim = (occurrences >= 3).then_some(DrawReason::ThreefoldRepetition);
+            assert_eq!(position.claimable_draw(&history), expected_claim, "{} occurrences", occurrences);
+            let expected_status = if occurrences >= 5 { GameStatus::Draw(DrawReason::FivefoldRepetition) } else { GameStatus::Ongoing };
+            assert_eq!(position.status(&history), expected_status, "{} occurrences", occurrences);
+        }
+    }
+
+    #[test]
+    fn insufficient_material() {
+        for fen in [
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1N2K3 w - - 0 1",
+            "4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/B1B1K3 w - - 0 1",
+        ] {
+            assert!(position(fen).has_insufficient_material(), "{}", fen);
+            assert_eq!(status(fen), GameStatus::Draw(DrawReason::InsufficientMaterial), "{}", fen);
+        }
+
+        for fen in [
+            "4k3/8/8/8/8/8/8/1BB1K3 w - - 0 1",
+            "2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/NN2K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1",
+            "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/R3K3 w - - 0 1",
+        ] {
+            assert!(!position(fen).has_insufficient_material(), "{}", fen);
+        }
+    }
+}
@@ -1,19 +1,27 @@
 use num_enum::TryFromPrimitive;
-use self::calculations::{intercect_with_player_pieces, is_square_in_check, is_empty};
+use self::calculations::{is_square_in_check, is_empty, precalculations::attack_sets};
 use deku::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
-use super::{
+mod attacks;
+mod game_status;
+mod move_generation;
+mod perft;
+mod static_exchange;
+mod validation;
+mod zobrist;
+
+use crate::definitions::{
     Square, 
     Piece, 
     PieceType, 
     SimplePosition, 
     Player, 
     Occupant, 
-    definitions:: {
-        Move, 
-        File,
-        Rank,
-    }
+    Move, 
+    MoveError,
+    File,
+    Rank,
 };
 
 
@@ -39,219 +47,362 @@ pub struct BitBoardPosition {
     white_queen_side_castle: bool,
     black_king_side_castle: bool,
     black_queen_side_castle: bool,
-    pub promotion_square: Square,
+    halfmove_clock: u16,
+    fullmove_number: u16,
+    // the rest is derived from the fields above, so it's rebuilt on read instead of stored
+    #[deku(skip, default = "zobrist::position_key(board, *to_play, [*white_king_side_castle, *white_queen_side_castle, *black_king_side_castle, *black_queen_side_castle], *en_passant_square)")]
+    hash: u64,
+    #[deku(skip, default = "mailbox_of(board)")]
+    mailbox: [u8; 64], //board layer of the piece on each square, or EMPTY_SQUARE
+    #[deku(skip, default = "player_occupancy_of(board)")]
+    player_occupancy: [u64; 2], //index with player enum
+}
+
+const EMPTY_SQUARE: u8 = 12;
+
+fn mailbox_of(board: &[u64; 12]) -> [u8; 64] {
+    let mut mailbox = [EMPTY_SQUARE; 64];
+    for (layer, pieces) in board.iter().enumerate() {
+        for square in BitBoard(*pieces) {
+            mailbox[square as usize] = layer as u8;
+        }
+    }
+    mailbox
+}
+
+fn player_occupancy_of(board: &[u64; 12]) -> [u64; 2] {
+    let (white, black) = board.split_at(6);
+    [white.iter().fold(0, |acc, layer| acc | layer), black.iter().fold(0, |acc, layer| acc | layer)]
 }
 
+/// Everything `unmake_move` needs to restore the position a move was played from.
+#[derive(Debug, Clone, Copy)]
+pub struct UndoRecord {
+    pub played_move: Move,
+    pub captured: Option<Piece>,
+    en_passant_square: Square,
+    white_king_side_castle: bool,
+    white_queen_side_castle: bool,
+    black_king_side_castle: bool,
+    black_queen_side_castle: bool,
+    halfmove_clock: u16,
+    fullmove_number: u16,
+    hash: u64,
+}
+
+/// A piece that can't leave the line between its king and an enemy slider
+/// without exposing the king.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pin {
+    pub pinned: Square,
+    pub pinner: Square,
+    /// The squares the pinned piece may still move to, capturing the pinner included.
+    pub ray: u64,
+}
 
 impl BitBoardPosition {
-    pub fn play_move(mut self, tentative_move: (Square, Square), attack_sets: &calculations::precalculations::PreComputedAttackSets) -> Result<Self, Self> {
-        if self.promotion_square != Square::Invalid { return Err(self); }
+    /// `promotion` names the piece a pawn reaching the last rank turns into and
+    /// has to be `None` for every other move.
+    pub fn play_move(&self, tentative_move: (Square, Square), promotion: Option<PieceType>) -> Result<Self, MoveError> {
+        let mut position = self.clone();
+        position.make_move(tentative_move, promotion)?;
+        Ok(position)
+    }
+
+    /// Plays the move in place. The position is left untouched when the move
+    /// is rejected, otherwise the returned record takes it back with `unmake_move`.
+    pub fn make_move(&mut self, tentative_move: (Square, Square), promotion: Option<PieceType>) -> Result<UndoRecord, MoveError> {
+        let detailed_move = Move::from_bitboard(self, tentative_move, promotion)?;
 
-        let tentative_move_result = Move::from_bitboard(&self, tentative_move);
+        let is_castle_move = detailed_move.is_castle_attempt();
 
-        if let Err(err) = tentative_move_result {
-            println!("{}\n\n", err);
-            return Err(self);
+        if is_castle_move {
+            self.validate_castle_move(&detailed_move)?;
+        } else {
+            self.validate_move(&detailed_move)?;
         }
 
-        let detailed_move = tentative_move_result.unwrap();
+        let is_promotion = detailed_move.piece.piece_type == PieceType::Pawn
+            && detailed_move.end.to_u64() & (Rank::First.to_u64() | Rank::Eight.to_u64()) != 0;
 
-        let mut is_valid_castle_move = false;
+        match (is_promotion, detailed_move.promotion) {
+            (true, None) => return Err(MoveError::PromotionRequired),
+            (true, Some(piece_type @ (PieceType::Pawn | PieceType::King))) => return Err(MoveError::InvalidPromotionPiece(piece_type)),
+            (false, Some(_)) => return Err(MoveError::UnexpectedPromotion),
+            _ => (),
+        }
 
-        if detailed_move.piece.piece_type == PieceType::King {
-            if self.to_play == Player::White
-                && detailed_move.start == Square::E1 {
-                    match detailed_move.end {
-                        Square::G1 => {
-                            if self.white_king_side_castle
-                                && !is_square_in_check(Square::E1, &self.board, self.to_play, attack_sets)
-                                && !is_square_in_check(Square::F1, &self.board, self.to_play, attack_sets)
-                                && !is_square_in_check(Square::G1, &self.board, self.to_play, attack_sets)
-                                && is_empty(Square::E1.to_u64() & Square::F1.to_u64() & Square::G1.to_u64(), &self.board){
-                                    is_valid_castle_move = true;
-                            }
-                        },
-                        Square::C1 => {
-                            if self.white_queen_side_castle
-                                && !is_square_in_check(Square::E1, &self.board, self.to_play, attack_sets)
-                                && !is_square_in_check(Square::D1, &self.board, self.to_play, attack_sets)
-                                && !is_square_in_check(Square::C1, &self.board, self.to_play, attack_sets)
-                                && is_empty(Square::E1.to_u64() & Square::D1.to_u64() & Square::C1.to_u64(), &self.board){
-                                    is_valid_castle_move = true;
-                            }
-                        },
-                        _ => ()
-                    }
-                } else if detailed_move.start == Square::E8 {
-                    match detailed_move.end {
-                        Square::G8 => {
-                            if self.black_king_side_castle
-                                && !is_square_in_check(Square::E8, &self.board, self.to_play, attack_sets)
-                                && !is_square_in_check(Square::F8, &self.board, self.to_play, attack_sets)
-                                && !is_square_in_check(Square::G8, &self.board, self.to_play, attack_sets)
-                                && is_empty(Square::E8.to_u64() & Square::F8.to_u64() & Square::G8.to_u64(), &self.board){
-                                    is_valid_castle_move = true;
-                                }
-                        },
-                        Square::C8 => {
-                            if self.black_queen_side_castle
-                                && !is_square_in_check(Square::E8, &self.board, self.to_play, attack_sets)
-                                && !is_square_in_check(Square::D8, &self.board, self.to_play, attack_sets)
-                                && !is_square_in_check(Square::C8, &self.board, self.to_play, attack_sets)
-                                && is_empty(Square::E8.to_u64() & Square::D8.to_u64() & Square::C8.to_u64(), &self.board){
-                                    is_valid_castle_move = true;
-                                }
-                        },
-                        _ => ()
-                    }
-                }
+        let undo = self.apply_move(&detailed_move, is_castle_move);
+
+        if !is_castle_move && calculations::is_king_in_check(&self.board, self.to_play.opponent()) {
+            self.unmake_move(undo);
+            return Err(MoveError::LeavesKingInCheck)
         }
 
-        let mut new_board = self.board.clone();
+        Ok(undo)
+    }
 
-        new_board[detailed_move.piece.piece_type as usize + (6 * self.to_play as usize)] += detailed_move.end.to_u64();
-        new_board[detailed_move.piece.piece_type as usize + (6 * self.to_play as usize)] -= detailed_move.start.to_u64();
+    fn apply_move(&mut self, detailed_move: &Move, is_castle_move: bool) -> UndoRecord {
+        let undo = UndoRecord {
+            played_move: *detailed_move,
+            captured: self.captured_piece(detailed_move),
+            en_passant_square: self.en_passant_square,
+            white_king_side_castle: self.white_king_side_castle,
+            white_queen_side_castle: self.white_queen_side_castle,
+            black_king_side_castle: self.black_king_side_castle,
+            black_queen_side_castle: self.black_queen_side_castle,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
+            hash: self.hash,
+        };
+
+        let own_offset = 6 * self.to_play as usize;
+        let end_piece_type = detailed_move.promotion.unwrap_or(detailed_move.piece.piece_type);
+        let mut hash = self.hash
+            ^ zobrist::piece_key(detailed_move.piece.piece_type as usize + own_offset, detailed_move.start)
+            ^ zobrist::piece_key(end_piece_type as usize + own_offset, detailed_move.end)
+            ^ zobrist::castling_key(self.white_king_side_castle, self.white_queen_side_castle, self.black_king_side_castle, self.black_queen_side_castle)
+            ^ zobrist::en_passant_key(self.capturable_en_passant_square())
+            ^ zobrist::side_key();
+
+        if let Some(captured) = undo.captured {
+            let opponent_offset = 6 * self.to_play.opponent() as usize;
+            let capture_square = captured_square(detailed_move, self.en_passant_square);
+            hash ^= zobrist::piece_key(captured.piece_type as usize + opponent_offset, capture_square);
+            self.remove_piece(captured.piece_type as usize + opponent_offset, capture_square);
+        }
 
-        if is_valid_castle_move {
-            let rook_start: Square;
-            let rook_end: Square;
+        self.remove_piece(detailed_move.piece.piece_type as usize + own_offset, detailed_move.start);
+        self.put_piece(end_piece_type as usize + own_offset, detailed_move.end);
 
-            match detailed_move.end {
-                Square::C1 => {
-                    rook_start = Square::A1;
-                    rook_end = Square::D1;
-                    self.white_queen_side_castle = false;
-                },
-                Square::G1 => {
-                    rook_start = Square::H1;
-                    rook_end = Square::F1;
-                    self.white_king_side_castle = false;
-                },
-                Square::C8 => {
-                    rook_start = Square::A8;
-                    rook_end = Square::D8;
-                    self.black_queen_side_castle = false;
+        if is_castle_move {
+            let (rook_start, rook_end) = castle_rook_squares(detailed_move.end);
+            hash ^= zobrist::piece_key(PieceType::Rook as usize + own_offset, rook_start)
+                ^ zobrist::piece_key(PieceType::Rook as usize + own_offset, rook_end);
+            self.remove_piece(PieceType::Rook as usize + own_offset, rook_start);
+            self.put_piece(PieceType::Rook as usize + own_offset, rook_end);
+        }
+
+        self.en_passant_square = Square::Invalid;
+        if detailed_move.piece.piece_type == PieceType::Pawn {
+            match self.to_play {
+                Player::White => {
+                    if detailed_move.start.to_u64() & Rank::Second.to_u64() != 0
+                        && detailed_move.end.to_u64() & Rank::Fourth.to_u64() != 0 {
+                            self.en_passant_square = Square::from_u64(detailed_move.start.to_u64() << 8);
+                        }
                 },
-                Square::G8 => {
-                    rook_start = Square::H8;
-                    rook_end = Square::F8;
-                    self.black_king_side_castle = false;
+                Player::Black => {
+                    if detailed_move.start.to_u64() & Rank::Seventh.to_u64() != 0
+                        && detailed_move.end.to_u64() & Rank::Fifth.to_u64() != 0 {
+                            self.en_passant_square = Square::from_u64(detailed_move.start.to_u64() >> 8);
+                        }
                 },
-                _ => panic!("There's bug, castle move already validated, but is invalid")
             }
+        }
 
-            new_board[PieceType::Rook as usize + (6 * self.to_play as usize)] += rook_end.to_u64();
-            new_board[PieceType::Rook as usize + (6 * self.to_play as usize)] -= rook_start.to_u64();
+        self.revoke_castling_rights(detailed_move.start);
+        self.revoke_castling_rights(detailed_move.end);
 
-            self.en_passant_square = Square::Invalid;
+        if detailed_move.piece.piece_type == PieceType::Pawn || undo.captured.is_some() {
+            self.halfmove_clock = 0;
         } else {
-            if let Err(err) = self.validate_move(&detailed_move, attack_sets) {
-                println!("{}\n\n", err);
-                return Err(self);
-            }
+            // FEN allows any u16, the clock stops at the top instead of overflowing
+            self.halfmove_clock = self.halfmove_clock.saturating_add(1);
+        }
 
-            if detailed_move.piece.piece_type == PieceType::Pawn 
-                && detailed_move.end == self.en_passant_square {
-                    match self.to_play {
-                        Player::White => {
-                            let mut layer = new_board[PieceType::Pawn as usize + 6];
-                            layer = layer - (layer & self.en_passant_square.to_u64() >> 8);
-                            new_board[PieceType::Pawn as usize + 6] = layer;
-                        },
-                        Player::Black => {
-                            let mut layer = new_board[PieceType::Pawn as usize];
-                            layer = layer - (layer & self.en_passant_square.to_u64() << 8);
-                            new_board[PieceType::Pawn as usize] = layer;
-                        },
-                    }
-            }
+        // the en passant square only counts once the opponent is to move
+        self.end_turn();
+        self.hash = hash
+            ^ zobrist::castling_key(self.white_king_side_castle, self.white_queen_side_castle, self.black_king_side_castle, self.black_queen_side_castle)
+            ^ zobrist::en_passant_key(self.capturable_en_passant_square());
 
-            for piece_type_determinant in 0..6 {
-                let mut layer = new_board[piece_type_determinant + (6 * self.to_play.opponent() as usize)];
+        undo
+    }
 
-                layer = layer - (layer & detailed_move.end.to_u64());
+    /// Takes back the move `undo` was returned for. Records have to be
+    /// unmade in the reverse order they were made in.
+    pub fn unmake_move(&mut self, undo: UndoRecord) {
+        self.to_play = self.to_play.opponent();
 
-                if (layer & detailed_move.end.to_u64()) != 0 {
-                    println!("Piece captured!");
-                }
+        let played_move = undo.played_move;
+        let own_offset = 6 * self.to_play as usize;
+        let opponent_offset = 6 * self.to_play.opponent() as usize;
 
-                new_board[piece_type_determinant + (6 * self.to_play.opponent() as usize)] = layer;
-            }
+        let end_piece_type = played_move.promotion.unwrap_or(played_move.piece.piece_type);
+        self.remove_piece(end_piece_type as usize + own_offset, played_move.end);
+        self.put_piece(played_move.piece.piece_type as usize + own_offset, played_move.start);
 
-            if calculations::is_king_in_check(&new_board, self.to_play, attack_sets) {
-                println!("King can't be in check");
-                return Err(self)
-            }
-            if detailed_move.piece.piece_type == PieceType::Pawn {
-                match self.to_play {
-                    Player::White => {
-                        if detailed_move.start.to_u64() & Rank::Second.to_u64() != 0
-                            && detailed_move.end.to_u64() & Rank::Fourth.to_u64() != 0 {
-                                self.en_passant_square = Square::from_u64(detailed_move.start.to_u64() << 8);
-                            }
-                    },
-                    Player::Black => {
-                        if detailed_move.start.to_u64() & Rank::Seventh.to_u64() != 0
-                            && detailed_move.end.to_u64() & Rank::Fifth.to_u64() != 0 {
-                                self.en_passant_square = Square::from_u64(detailed_move.start.to_u64() >> 8);
-                            }
-                    },
-                }
-            } else {
-                self.en_passant_square = Square::Invalid;
-            }
+        if played_move.is_castle_attempt() {
+            let (rook_start, rook_end) = castle_rook_squares(played_move.end);
+            self.remove_piece(PieceType::Rook as usize + own_offset, rook_end);
+            self.put_piece(PieceType::Rook as usize + own_offset, rook_start);
+        }
 
-            match detailed_move.start {
-                Square::E1 => {
-                    self.white_king_side_castle = false;
-                    self.white_queen_side_castle = false;
-                },
-                Square::E8 => {
-                    self.black_king_side_castle = false;
-                    self.black_queen_side_castle = false;
-                },
-                Square::A1 => {
-                    self.white_queen_side_castle = false;
-                },
-                Square::H1 => {
-                    self.white_king_side_castle = false;
-                },
-                Square::A8 => {
-                    self.black_queen_side_castle = false;
-                },
-                Square::H8 => {
-                    self.black_king_side_castle = false;
-                },
-                _ => ()
-            }
+        if let Some(captured) = undo.captured {
+            self.put_piece(captured.piece_type as usize + opponent_offset, captured_square(&played_move, undo.en_passant_square));
         }
 
-        self.board = new_board;
+        self.en_passant_square = undo.en_passant_square;
+        self.white_king_side_castle = undo.white_king_side_castle;
+        self.white_queen_side_castle = undo.white_queen_side_castle;
+        self.black_king_side_castle = undo.black_king_side_castle;
+        self.black_queen_side_castle = undo.black_queen_side_castle;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.fullmove_number = undo.fullmove_number;
+        self.hash = undo.hash;
+    }
 
-        if detailed_move.piece.piece_type == PieceType::Pawn 
-            && detailed_move.end.to_u64() & (Rank::First.to_u64() | Rank::Eight.to_u64()) != 0 {
-                self.promotion_square = detailed_move.end;
-                return Ok(self)
-            }
+    /// What stands on `square`, read from the mailbox rather than the bitboards.
+    pub fn piece_at(&self, square: Square) -> Option<Piece> {
+        if square == Square::Invalid { return None; }
+        piece_on_layer(self.mailbox[square as usize])
+    }
 
+    /// Every square `player` has a piece on.
+    pub fn player_occupancy(&self, player: Player) -> u64 {
+        self.player_occupancy[player as usize]
+    }
+
+    /// Every square with a piece on it.
+    pub fn occupancy(&self) -> u64 {
+        self.player_occupancy[Player::White as usize] | self.player_occupancy[Player::Black as usize]
+    }
+
+    /// Starts at 1 and goes up after each of Black's moves.
+    pub fn fullmove_number(&self) -> u16 {
+        self.fullmove_number
+    }
+
+    // the bitboards, mailbox and player occupancy only ever change together
+    // through these two
+    fn put_piece(&mut self, layer: usize, square: Square) {
+        self.board[layer] |= square.to_u64();
+        self.mailbox[square as usize] = layer as u8;
+        self.player_occupancy[layer / 6] |= square.to_u64();
+    }
+
+    fn remove_piece(&mut self, layer: usize, square: Square) {
+        self.board[layer] &= !square.to_u64();
+        self.mailbox[square as usize] = EMPTY_SQUARE;
+        self.player_occupancy[layer / 6] &= !square.to_u64();
+    }
+
+    fn captured_piece(&self, detailed_move: &Move) -> Option<Piece> {
+        let opponent = self.to_play.opponent();
+
+        if detailed_move.piece.piece_type == PieceType::Pawn && detailed_move.end == self.en_passant_square {
+            return Some(Piece { piece_type: PieceType::Pawn, owner: opponent });
+        }
+
+        self.piece_at(detailed_move.end).filter(|piece| piece.owner == opponent)
+    }
+
+    fn end_turn(&mut self) {
+        if self.to_play == Player::Black {
+            self.fullmove_number = self.fullmove_number.saturating_add(1);
+        }
         self.to_play = self.to_play.opponent();
-        Ok(self)
     }
 
-    fn validate_move(&self, tentative_move: &Move, attack_sets: &calculations::precalculations::PreComputedAttackSets) -> Result<(), String> {
-        if self.to_play != tentative_move.piece.owner { return Err("Player doesn't own this piece".to_string()); }
+    fn validate_castle_move(&self, tentative_move: &Move) -> Result<(), MoveError> {
+        let (has_right, rook_start, path, king_path) = match (self.to_play, tentative_move.start, tentative_move.end) {
+            (Player::White, Square::E1, Square::G1) => (self.white_king_side_castle, Square::H1, Square::F1.to_u64() | Square::G1.to_u64(), [Square::F1, Square::G1]),
+            (Player::White, Square::E1, Square::C1) => (self.white_queen_side_castle, Square::A1, Square::D1.to_u64() | Square::C1.to_u64() | Square::B1.to_u64(), [Square::D1, Square::C1]),
+            (Player::Black, Square::E8, Square::G8) => (self.black_king_side_castle, Square::H8, Square::F8.to_u64() | Square::G8.to_u64(), [Square::F8, Square::G8]),
+            (Player::Black, Square::E8, Square::C8) => (self.black_queen_side_castle, Square::A8, Square::D8.to_u64() | Square::C8.to_u64() | Square::B8.to_u64(), [Square::D8, Square::C8]),
+            _ => return Err(MoveError::IllegalPieceMove(PieceType::King)),
+        };
+
+        if !has_right || self.board[PieceType::Rook as usize + (6 * self.to_play as usize)] & rook_start.to_u64() == 0 {
+            return Err(MoveError::CastlingNotAllowed);
+        }
+
+        if !is_empty(path, &self.board) {
+            return Err(MoveError::CastlingBlocked);
+        }
+
+        if is_square_in_check(tentative_move.start, &self.board, self.to_play) {
+            return Err(MoveError::CastlingOutOfCheck);
+        }
+
+        if king_path.iter().any(|square| is_square_in_check(*square, &self.board, self.to_play)) {
+            return Err(MoveError::CastlingThroughCheck);
+        }
+
+        Ok(())
+    }
+
+    fn board_after_move(&self, detailed_move: &Move, is_castle_move: bool) -> [u64; 12] {
+        let mut new_board = self.board;
+        let own_offset = 6 * self.to_play as usize;
+        let opponent_offset = 6 * self.to_play.opponent() as usize;
 
         for piece_type_determinant in 0..6 {
-            if (self.board[piece_type_determinant + (6 * self.to_play as usize)] & tentative_move.end.to_u64()) != 0 {
-                return Err("Player already has a piece occupying the end square".to_string());
-            }
-        }            
+            new_board[piece_type_determinant + opponent_offset] &= !detailed_move.end.to_u64();
+        }
+
+        if detailed_move.piece.piece_type == PieceType::Pawn
+            && detailed_move.end == self.en_passant_square {
+                let captured_pawn = match self.to_play {
+                    Player::White => self.en_passant_square.to_u64() >> 8,
+                    Player::Black => self.en_passant_square.to_u64() << 8,
+                };
+                new_board[PieceType::Pawn as usize + opponent_offset] &= !captured_pawn;
+        }
+
+        let end_piece_type = detailed_move.promotion.unwrap_or(detailed_move.piece.piece_type);
+        new_board[detailed_move.piece.piece_type as usize + own_offset] &= !detailed_move.start.to_u64();
+        new_board[end_piece_type as usize + own_offset] |= detailed_move.end.to_u64();
+
+        if is_castle_move {
+            let (rook_start, rook_end) = castle_rook_squares(detailed_move.end);
+
+            new_board[PieceType::Rook as usize + own_offset] &= !rook_start.to_u64();
+            new_board[PieceType::Rook as usize + own_offset] |= rook_end.to_u64();
+        }
+
+        new_board
+    }
+
+    fn revoke_castling_rights(&mut self, square: Square) {
+        match square {
+            Square::E1 => {
+                self.white_king_side_castle = false;
+                self.white_queen_side_castle = false;
+            },
+            Square::E8 => {
+                self.black_king_side_castle = false;
+                self.black_queen_side_castle = false;
+            },
+            Square::A1 => {
+                self.white_queen_side_castle = false;
+            },
+            Square::H1 => {
+                self.white_king_side_castle = false;
+            },
+            Square::A8 => {
+                self.black_queen_side_castle = false;
+            },
+            Square::H8 => {
+                self.black_king_side_castle = false;
+            },
+            _ => ()
+        }
+    }
+
+    fn validate_move(&self, tentative_move: &Move) -> Result<(), MoveError> {
+        if self.to_play != tentative_move.piece.owner { return Err(MoveError::NotYourPiece); }
+
+        if self.player_occupancy(self.to_play) & tentative_move.end.to_u64() != 0 {
+            return Err(MoveError::OccupiedByOwnPiece);
+        }
 
         match tentative_move.piece.piece_type {
             PieceType::Knight => {
                 let intersection = calculations::knight_attacks(tentative_move.start.to_u64()) & tentative_move.end.to_u64();
                 if intersection != 0 { return Ok(()); }
-                Err("Illegal knight move".to_string())
+                Err(MoveError::IllegalPieceMove(PieceType::Knight))
             },
             PieceType::Pawn => {
                 let start = tentative_move.start.to_u64();
@@ -262,155 +413,92 @@ impl BitBoardPosition {
                     if tentative_move.end == self.en_passant_square {
                         return Ok(());
                     }
-                    if intercect_with_player_pieces(tentative_move.end.to_u64(), &self.board, self.to_play.opponent()) {
+                    if self.player_occupancy(self.to_play.opponent()) & end != 0 {
                         return Ok(());
                     }
-                    return Err("Diagonal pawn moves need to be a capture".to_string())
+                    return Err(MoveError::PawnCaptureWithoutTarget)
                 }
                 
                 if (calculations::pawn_moves(start, owner) & end) != 0 {
-                    if !intercect_with_player_pieces(tentative_move.end.to_u64(), &self.board, self.to_play.opponent()) 
-                        && !intercect_with_player_pieces(tentative_move.end.to_u64(), &self.board, self.to_play) {
-                        return Ok(());
+                    if self.occupancy() & end != 0 {
+                        return Err(MoveError::PawnCannotCaptureForward)
                     }
-                    return Err("Pawn cannot attack forward".to_string())
-                }
-
-                Err("Illegal pawn move".to_string())
-            },
-            PieceType::Rook => {
-                let start = tentative_move.start;
-                let end = tentative_move.end;
-
-                let mut possible_moves = 0;
-
-                for direction_index in 0..4 {
-                    for next_square_index in 0..7 {
-                        let next_square = &attack_sets.orthogonals[start as usize][direction_index][next_square_index];
-                        if next_square == &Square::Invalid { break; }
-
-                        if intercect_with_player_pieces(next_square.to_u64(), &self.board, self.to_play) { break; }
-
-                        possible_moves = possible_moves | next_square.to_u64();
-
-                        if intercect_with_player_pieces(next_square.to_u64(), &self.board, self.to_play.opponent()) { break; }
+                    let skipped_square = match owner {
+                        Player::White => start << 8,
+                        Player::Black => start >> 8,
+                    };
+                    if skipped_square != end && self.occupancy() & skipped_square != 0 {
+                        return Err(MoveError::BlockedPath)
                     }
+                    return Ok(());
                 }
 
-                if possible_moves & end.to_u64() != 0 {
-                    Ok(())
-                } else {
-                    Err("Illegal rook move".to_string())
-                }
+                Err(MoveError::IllegalPieceMove(PieceType::Pawn))
             },
-            PieceType::Bishop => {
+            PieceType::Rook | PieceType::Bishop | PieceType::Queen => {
+                let piece_type = tentative_move.piece.piece_type;
                 let start = tentative_move.start;
                 let end = tentative_move.end;
-
-                let mut possible_moves = 0;
-
-                for direction_index in 0..4 {
-                    for next_square_index in 0..7 {
-                        let next_square = &attack_sets.diagonals[start as usize][direction_index][next_square_index];
-                        if next_square == &Square::Invalid { break; }
-
-                        if intercect_with_player_pieces(next_square.to_u64(), &self.board, self.to_play) { break; }
-
-                        possible_moves = possible_moves | next_square.to_u64();
-
-                        if intercect_with_player_pieces(next_square.to_u64(), &self.board, self.to_play.opponent()) { break; }
-                    }
-                }
+                let possible_moves = attack_sets().sliding_attacks(start, piece_type, self.occupancy());
 
                 if possible_moves & end.to_u64() != 0 {
                     return Ok(())
-                }  
-                Err("Illegal bishop move".to_string())
-            },
-            PieceType::Queen => {
-                let mut rook_move = tentative_move.clone();
-                let mut bishop_move = tentative_move.clone();
-                rook_move.piece.piece_type = PieceType::Rook;
-                bishop_move.piece.piece_type = PieceType::Bishop;
-                if self.validate_move(&rook_move, attack_sets).is_ok() 
-                    || self.validate_move(&bishop_move, attack_sets).is_ok() {
-                    return Ok(())
                 }
-                Err("Illegal queen move".to_string())
+                if attack_sets().attacks(start, piece_type) & end.to_u64() != 0 {
+                    return Err(MoveError::BlockedPath)
+                }
+                Err(MoveError::IllegalPieceMove(piece_type))
             },
             PieceType::King => {
                 let intersection = calculations::king_moves(tentative_move.start.to_u64()) & tentative_move.end.to_u64();
                 if intersection != 0 { return Ok(()); }
-                Err("Illegal king move".to_string())
+                Err(MoveError::IllegalPieceMove(PieceType::King))
             }
         }
     }
 
-    pub fn promote(mut self, piece: PieceType) -> Result<Self, Self> {
-        if self.promotion_square == Square::Invalid { return Err(self); }
-
-        self.board[PieceType::Pawn as usize + (6 * self.to_play as usize)] -= self.promotion_square.to_u64();
-        self.board[piece as usize + (6 * self.to_play as usize)] += self.promotion_square.to_u64();
-
-        self.to_play = self.to_play.opponent();
-        self.promotion_square = Square::Invalid;
-
-        Ok(self)
-    }
-
     pub fn from_position(position: &SimplePosition) -> Self {
-        const PIECE_TYPE_COUNT: usize = 6;
-
-        let mut board: [u64; PIECE_TYPE_COUNT * 2] = [0; PIECE_TYPE_COUNT * 2];
-
-        for (square_index, occupant) in position.board.iter().enumerate() {
-            let piece_result = occupant.piece();
-            if piece_result.is_none() { continue; }
-            let piece = piece_result.unwrap();
-            let bit_board_layer = match piece.owner {
-                Player::White => (piece.piece_type as usize) + (PIECE_TYPE_COUNT * Player::White as usize),
-                Player::Black => (piece.piece_type as usize) + (PIECE_TYPE_COUNT * Player::Black as usize),
-            };
-            board[bit_board_layer] += (2 as u64).pow(square_index as u32);
-        }
-
-        Self {
+        let mut bit_board_position = Self {
             to_play: position.to_play, 
             en_passant_square: position.en_passant_square,
             white_king_side_castle: position.white_king_side_castle,
-            white_queen_side_castle: position.black_queen_side_castle,
-            black_king_side_castle: position.white_king_side_castle,
+            white_queen_side_castle: position.white_queen_side_castle,
+            black_king_side_castle: position.black_king_side_castle,
             black_queen_side_castle: position.black_queen_side_castle,
-            promotion_square: position.promotion_square,
-            board,
+            halfmove_clock: position.halfmove_clock,
+            fullmove_number: position.fullmove_number,
+            hash: 0,
+            board: [0; 12],
+            mailbox: [EMPTY_SQUARE; 64],
+            player_occupancy: [0; 2],
+        };
+
+        for (square_index, occupant) in position.board.iter().enumerate() {
+            if let Some(piece) = occupant.piece() {
+                let square = Square::try_from_primitive(square_index as u8).unwrap();
+                bit_board_position.put_piece(piece.piece_type as usize + (6 * piece.owner as usize), square);
+            }
         }
+
+        bit_board_position.hash = bit_board_position.compute_zobrist_key();
+        bit_board_position
     }
 
     pub fn to_position(&self) -> SimplePosition {
-        let mut board = [Occupant::None; 64];
-        const PIECE_TYPE_COUNT: u8 = 6;
-
-        for (i, layer) in self.board.iter().enumerate() {
-            for square_index in 0..64 {
-                let intersection_check = (2 as u64).pow(square_index as u32);
-                if (layer & intersection_check) == intersection_check {
-                    let player = if i < 6 { Player::White } else { Player::Black };
-                    board[square_index] = Occupant::Piece(Piece {
-                        owner: player,
-                        piece_type: PieceType::try_from(i as u8 - (PIECE_TYPE_COUNT * player as u8)).unwrap()
-                    })
-                }
-            }
-        }
+        let board = self.mailbox.map(|layer| match piece_on_layer(layer) {
+            Some(piece) => Occupant::Piece(piece),
+            None => Occupant::None,
+        });
 
         SimplePosition {
             to_play: self.to_play,
             en_passant_square: self.en_passant_square,
             white_king_side_castle: self.white_king_side_castle,
-            white_queen_side_castle: self.black_queen_side_castle,
-            black_king_side_castle: self.white_king_side_castle,
+            white_queen_side_castle: self.white_queen_side_castle,
+            black_king_side_castle: self.black_king_side_castle,
             black_queen_side_castle: self.black_queen_side_castle,
-            promotion_square: self.promotion_square,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number,
             board,
         }
     }
@@ -430,6 +518,48 @@ impl BitBoardPosition {
     }
 }
 
+// the same JSON as SimplePosition, bitboards are an implementation detail
+impl Serialize for BitBoardPosition {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.to_position().serialize(serializer)
+    }
+}
+
+/// Like `from_position`, the position isn't validated.
+impl<'de> Deserialize<'de> for BitBoardPosition {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(BitBoardPosition::from_position(&SimplePosition::deserialize(deserializer)?))
+    }
+}
+
+fn piece_on_layer(layer: u8) -> Option<Piece> {
+    if layer == EMPTY_SQUARE { return None; }
+    Some(Piece {
+        piece_type: PieceType::try_from_primitive(layer % 6).unwrap(),
+        owner: if layer < 6 { Player::White } else { Player::Black },
+    })
+}
+
+fn castle_rook_squares(king_end: Square) -> (Square, Square) {
+    match king_end {
+        Square::C1 => (Square::A1, Square::D1),
+        Square::G1 => (Square::H1, Square::F1),
+        Square::C8 => (Square::A8, Square::D8),
+        Square::G8 => (Square::H8, Square::F8),
+        _ => panic!("There's bug, castle move already validated, but is invalid")
+    }
+}
+
+fn captured_square(played_move: &Move, en_passant_square: Square) -> Square {
+    if played_move.piece.piece_type != PieceType::Pawn || played_move.end != en_passant_square {
+        return played_move.end;
+    }
+    match played_move.piece.owner {
+        Player::White => Square::from_u64(played_move.end.to_u64() >> 8),
+        Player::Black => Square::from_u64(played_move.end.to_u64() << 8),
+    }
+}
+
 pub struct BitBoard(pub u64);
 
 impl BitBoard {
@@ -446,38 +576,56 @@ impl BitBoard {
     }
 }
 
+impl Iterator for BitBoard {
+    type Item = Square;
+
+    fn next(&mut self) -> Option<Square> {
+        if self.0 == 0 { return None; }
+        let square = Square::try_from_primitive(self.0.trailing_zeros() as u8).unwrap();
+        self.0 &= self.0 - 1;
+        Some(square)
+    }
+}
+
 impl Move {
-    fn from_bitboard(position: &BitBoardPosition, squares: (Square, Square)) -> Result<Self, &str> {
+    fn from_bitboard(position: &BitBoardPosition, squares: (Square, Square), promotion: Option<PieceType>) -> Result<Self, MoveError> {
 
         let start_square = squares.0;
         let end_square = squares.1;
 
-        for piece_type_determinant in 0..6 {
-            if (position.board[piece_type_determinant + (6 * position.to_play as usize)] & start_square.to_u64()) != 0 {
-                return Ok(Move {
-                    start: start_square,
-                    end: end_square,
-                    piece: Piece {
-                        owner: position.to_play,
-                        piece_type: PieceType::try_from_primitive(piece_type_determinant as u8).unwrap(),
-                    }
-                })
-            }
+        if start_square == Square::Invalid || end_square == Square::Invalid {
+            return Err(MoveError::InvalidSquare);
+        }
+
+        match position.piece_at(start_square) {
+            Some(piece) if piece.owner == position.to_play => Ok(Move {
+                start: start_square,
+                end: end_square,
+                piece,
+                promotion,
+            }),
+            Some(_) => Err(MoveError::NotYourPiece),
+            None => Err(MoveError::EmptySquare),
         }
-        Err("Square is empty")
+    }
+
+    pub fn is_castle_attempt(&self) -> bool {
+        self.piece.piece_type == PieceType::King
+            && (self.start as i8 - self.end as i8).abs() == 2
     }
 }
 
 impl File {
-    fn to_u64(&self) -> u64 {
-        (0..8).into_iter().fold(0, |acc, row_numer| -> u64 { acc + 2u64.pow((row_numer * 8) + self.to_owned() as u32) })
+    /// Every square on the file.
+    pub fn to_u64(self) -> u64 {
+        0x0101_0101_0101_0101 << self as u32
     }
 }
 
 impl Rank {
-    fn to_u64(&self) -> u64 {
-        let descriminant = self.to_owned() as u32;
-        (0..8).into_iter().fold(0, |acc, colum_number| -> u64 { acc + 2u64.pow((colum_number) + descriminant) }) << (descriminant * 7)
+    /// Every square on the rank.
+    pub fn to_u64(self) -> u64 {
+        0xff << (self as u32 * 8)
     }
 }
 
@@ -486,57 +634,46 @@ pub mod calculations {
 
     use crate::definitions::{Rank, PieceType, Square};
 
+    use self::precalculations::attack_sets;
     use super::{ File, Player, };
 
-    pub fn is_square_in_check(square: Square, board: &[u64; 12], player: Player, attack_sets: &precalculations::PreComputedAttackSets) -> bool {
-        if knight_attacks(square.to_u64()) & board[PieceType::Knight as usize + (6 * player.opponent() as usize)] != 0 {
-            return true
-        } 
-
-        if king_moves(square.to_u64()) & board[PieceType::King as usize + (6 * player.opponent() as usize)] != 0 {
-            return true
-        } 
-
-        if pawn_attacks(square.to_u64(), player) & board[PieceType::Pawn as usize + (6 * player.opponent() as usize)] != 0 {
-            return true
-        } 
-
-        for direction_index in 0..4 {
-            for next_square_index in 0..7 {
-                let next_square = &attack_sets.orthogonals[Square::from_u64(square.to_u64()) as usize][direction_index][next_square_index];
-
-                if next_square == &Square::Invalid { break; }
-
-                if next_square.to_u64() & (board[PieceType::Rook as usize + (6 * player.opponent() as usize)] 
-                | board[PieceType::Queen as usize + (6 * player.opponent() as usize)]) !=0 {
-                    return true;
-                }
-
-                if !is_empty(next_square.to_u64(), board) { break; }
-            }
-        }
+    pub fn is_square_in_check(square: Square, board: &[u64; 12], player: Player) -> bool {
+        attackers_to(square, board, player.opponent(), occupancy(board)) != 0
+    }
 
-        for direction_index in 0..4 {
-            for next_square_index in 0..7 {
-                let next_square = &attack_sets.diagonals[Square::from_u64(square.to_u64()) as usize][direction_index][next_square_index];
+    /// Every piece of `by_player` attacking `square`, with sliders stopped by
+    /// the pieces in `occupancy` rather than the board so they can be looked
+    /// through.
+    pub fn attackers_to(square: Square, board: &[u64; 12], by_player: Player, occupancy: u64) -> u64 {
+        let layer = |piece_type: PieceType| board[piece_type as usize + (6 * by_player as usize)];
+        let queens = layer(PieceType::Queen);
+
+        knight_attacks(square.to_u64()) & layer(PieceType::Knight)
+            | king_moves(square.to_u64()) & layer(PieceType::King)
+            | pawn_attacks(square.to_u64(), by_player.opponent()) & layer(PieceType::Pawn)
+            | attack_sets().sliding_attacks(square, PieceType::Rook, occupancy) & (layer(PieceType::Rook) | queens)
+            | attack_sets().sliding_attacks(square, PieceType::Bishop, occupancy) & (layer(PieceType::Bishop) | queens)
+    }
 
-                if next_square == &Square::Invalid { break; }
+    /// Every square `player` attacks, whether empty or occupied by either side.
+    pub fn attacked_squares(board: &[u64; 12], player: Player, occupancy: u64) -> u64 {
+        let layer = |piece_type: PieceType| board[piece_type as usize + (6 * player as usize)];
 
-                if next_square.to_u64() & (board[PieceType::Bishop as usize + (6 * player.opponent() as usize)] 
-                | board[PieceType::Queen as usize + (6 * player.opponent() as usize)]) !=0 {
-                    return true;
-                }
+        let mut attacked = knight_attacks(layer(PieceType::Knight))
+            | king_moves(layer(PieceType::King))
+            | pawn_attacks(layer(PieceType::Pawn), player);
 
-                if !is_empty(next_square.to_u64(), board) { break; }
+        for piece_type in [PieceType::Rook, PieceType::Bishop, PieceType::Queen] {
+            for square in super::BitBoard(layer(piece_type)) {
+                attacked |= attack_sets().sliding_attacks(square, piece_type, occupancy);
             }
         }
-
-        false
+        attacked
     }
 
-    pub fn is_king_in_check(board: &[u64; 12], player: Player, attack_sets: &precalculations::PreComputedAttackSets) -> bool {
+    pub fn is_king_in_check(board: &[u64; 12], player: Player) -> bool {
         let king_position = board[PieceType::King as usize + (6 * player as usize)];
-        is_square_in_check(Square::from_u64(king_position), board, player, attack_sets)
+        is_square_in_check(Square::from_u64(king_position), board, player)
     }
 
     pub fn is_empty(map: u64, board: &[u64; 12]) -> bool {
@@ -544,6 +681,10 @@ pub mod calculations {
           || intercect_with_player_pieces(map, board, Player::Black))
     }
 
+    pub fn occupancy(board: &[u64; 12]) -> u64 {
+        board.iter().fold(0, |acc, layer| acc | layer)
+    }
+
     pub fn intercect_with_player_pieces(map: u64, board: &[u64; 12], player: Player) -> bool {
         for piece_type_determinant in 0..6 {
             if (board[piece_type_determinant + (6 * player as usize)] & map) != 0 {
@@ -574,14 +715,28 @@ pub mod calculations {
     }
 
     pub fn king_moves(positions: u64) -> u64 {
-        (positions << 7)
+        (positions << 7) & (u64::MAX ^ File::H.to_u64())
         | (positions << 8)
-        | (positions << 9)
-        | (positions << 1)
-        | (positions >> 7)
+        | (positions << 9) & (u64::MAX ^ File::A.to_u64())
+        | (positions << 1) & (u64::MAX ^ File::A.to_u64())
+        | (positions >> 7) & (u64::MAX ^ File::A.to_u64())
         | (positions >> 8)
-        | (positions >> 9)
-        | (positions >> 1)
+        | (positions >> 9) & (u64::MAX ^ File::H.to_u64())
+        | (positions >> 1) & (u64::MAX ^ File::H.to_u64())
+    }
+
+    pub fn ray_attacks(rays: &[[Square; 7]; 4], occupancy: u64) -> u64 {
+        let mut attack_map = 0;
+        for ray in rays {
+            for next_square in ray {
+                if next_square == &Square::Invalid { break; }
+
+                attack_map |= next_square.to_u64();
+
+                if next_square.to_u64() & occupancy != 0 { break; }
+            }
+        }
+        attack_map
     }
 
     pub fn pawn_moves(positions: u64, player: Player) -> u64 {
@@ -600,17 +755,48 @@ pub mod calculations {
 
     pub mod precalculations {
 
+        use std::sync::OnceLock;
+
         use num_enum::TryFromPrimitive;
         use deku::prelude::*;
 
         use crate::{bit_board::{ PieceType, Rank, File }, definitions::Square};
 
+        static ATTACK_SETS: OnceLock<PreComputedAttackSets> = OnceLock::new();
+
+        /// The attack sets shared by every position, built the first time
+        /// they are needed.
+        pub fn attack_sets() -> &'static PreComputedAttackSets {
+            ATTACK_SETS.get_or_init(build_piece_attack_set)
+        }
+
         #[derive(DekuRead, DekuWrite)]
         pub struct PreComputedAttackSets {
             pub attacks: [[u64; 64]; 3],
             pub blockers: [[u64; 64]; 3],
             pub diagonals: [[[Square; 7]; 4]; 64],
             pub orthogonals: [[[Square; 7]; 4]; 64],
+            pub magics: [[Magic; 64]; 2], //index with the rook and bishop precalculated index
+            pub between: [[u64; 64]; 64],
+            pub magic_attacks_len: u32,
+            #[deku(count = "magic_attacks_len")]
+            pub magic_attacks: Vec<u64>,
+        }
+
+        /// Maps every blocker arrangement inside `mask` to a slot of the
+        /// shared `magic_attacks` table.
+        #[derive(Debug, Default, Clone, Copy, DekuRead, DekuWrite)]
+        pub struct Magic {
+            pub mask: u64,
+            pub magic: u64,
+            pub shift: u8,
+            pub offset: u32,
+        }
+
+        impl Magic {
+            fn index(&self, occupancy: u64) -> usize {
+                self.offset as usize + ((occupancy & self.mask).wrapping_mul(self.magic) >> self.shift) as usize
+            }
         }
 
         impl PreComputedAttackSets {
@@ -621,6 +807,27 @@ pub mod calculations {
             pub fn blockers(&self, square: Square, piece: PieceType) -> u64 {
                 self.blockers[piece.precalculated_index()][square as usize]
             }
+
+            /// Squares strictly between two squares sharing a rank, file or
+            /// diagonal, empty if they don't share one.
+            pub fn between(&self, from: Square, to: Square) -> u64 {
+                self.between[from as usize][to as usize]
+            }
+
+            /// Squares a rook, bishop or queen on `square` attacks given every
+            /// occupied square on the board, up to and including the first blocker.
+            pub fn sliding_attacks(&self, square: Square, piece: PieceType, occupancy: u64) -> u64 {
+                match piece {
+                    PieceType::Queen => {
+                        self.sliding_attacks(square, PieceType::Rook, occupancy)
+                            | self.sliding_attacks(square, PieceType::Bishop, occupancy)
+                    },
+                    _ => {
+                        let magic = &self.magics[piece.precalculated_index()][square as usize];
+                        self.magic_attacks[magic.index(occupancy)]
+                    },
+                }
+            }
         }
 
         impl PieceType {
@@ -809,12 +1016,149 @@ pub mod calculations {
             }
 
 
+            let mut magics = [[Magic::default(); 64]; 2];
+            let mut magic_attacks = vec![];
+
+            for square_index in 0..64 {
+                magics[PieceType::Rook.precalculated_index()][square_index] = build_magic(
+                    blockers[PieceType::Rook.precalculated_index()][square_index],
+                    ROOK_MAGICS[square_index],
+                    &orthogonals[square_index],
+                    &mut magic_attacks,
+                );
+                magics[PieceType::Bishop.precalculated_index()][square_index] = build_magic(
+                    blockers[PieceType::Bishop.precalculated_index()][square_index],
+                    BISHOP_MAGICS[square_index],
+                    &diagonals[square_index],
+                    &mut magic_attacks,
+                );
+            }
+
+            let mut between = [[0; 64]; 64];
+            for square_index in 0..64 {
+                for ray in orthogonals[square_index].iter().chain(diagonals[square_index].iter()) {
+                    let mut path = 0;
+                    for next_square in ray.iter().take_while(|next_square| **next_square != Square::Invalid) {
+                        between[square_index][*next_square as usize] = path;
+                        path |= next_square.to_u64();
+                    }
+                }
+            }
+
             PreComputedAttackSets{
                 attacks,
                 blockers,
                 diagonals,
                 orthogonals,
+                magics,
+                between,
+                magic_attacks_len: magic_attacks.len() as u32,
+                magic_attacks,
             }
         }
+
+        // walks every blocker arrangement inside `mask` and appends the
+        // square's slots of the shared table, indexed by `magic`
+        fn build_magic(mask: u64, magic: u64, rays: &[[Square; 7]; 4], magic_attacks: &mut Vec<u64>) -> Magic {
+            let bits = mask.count_ones();
+            let entry = Magic {
+                mask,
+                magic,
+                shift: (64 - bits) as u8,
+                offset: magic_attacks.len() as u32,
+            };
+
+            magic_attacks.resize(magic_attacks.len() + (1 << bits), 0);
+
+            let mut occupancy: u64 = 0;
+            loop {
+                let attack_map = super::ray_attacks(rays, occupancy);
+                let slot = &mut magic_attacks[entry.index(occupancy)];
+                debug_assert!(*slot == 0 || *slot == attack_map, "magic collision");
+                *slot = attack_map;
+                occupancy = occupancy.wrapping_sub(mask) & mask;
+                if occupancy == 0 { break; }
+            }
+
+            entry
+        }
+
+        // sparse multipliers found by random search over the blocker masks
+        // built above, each maps every blocker arrangement to a slot without
+        // two different attack sets colliding
+        const ROOK_MAGICS: [u64; 64] = [
+            0x0080_0090_8064_C000, 0x0040_2000_4000_1000, 0x0180_1000_80A0_010A, 0x8880_0410_0080_0800,
+            0x1200_1002_0120_0804, 0x0200_0200_0401_1008, 0x2180_0100_0080_0600, 0x0200_0050_8821_0204,
+            0x0000_8000_8020_4001, 0x1000_8040_0080_2001, 0x8240_8010_0020_0080, 0x8611_0010_0420_0900,
+            0x0081_8080_0C00_1800, 0x0100_8002_0080_0400, 0x0A02_0001_0200_0408, 0x8020_8023_0010_4280,
+            0x0080_0040_0040_2000, 0xE010_1040_0040_2000, 0x0800_8080_1000_2000, 0xA280_2100_0810_0100,
+            0x0001_8180_1400_0800, 0xA002_0101_0008_0400, 0x0008_0400_8802_0130, 0x0001_0200_0404_8845,
+            0x0081_8262_8000_4004, 0x2020_8109_0028_4000, 0x0200_1000_8080_2000, 0x0200_0800_8010_0080,
+            0x8083_0801_0010_0500, 0x4406_0009_0100_0400, 0x0005_0200_8080_0100, 0x0090_2042_0000_8114,
+            0x0010_4000_9480_0420, 0x0900_8040_0080_2002, 0x0201_0018_4100_2000, 0x4100_0800_8080_1000,
+            0x4540_0400_8080_0800, 0x0000_8004_0080_0200, 0x9281_8001_0080_8200, 0x8004_0481_0200_0854,
+            0x4420_8020_4000_8006, 0x0880_5000_2000_4002, 0x0801_2002_4105_0010, 0x8400_0800_1000_8080,
+            0x0008_0005_0009_0010, 0x0082_0090_8402_0008, 0x4012_0001_0802_0004, 0x9000_104D_0886_0004,
+            0x2004_2041_1480_0100, 0x0148_8021_1240_0300, 0x0202_8420_0010_0880, 0x001B_0800_8090_0080,
+            0x001A_0020_0810_0600, 0x0004_0080_0402_0080, 0x5181_0006_0004_0300, 0x0000_0444_0112_8A00,
+            0x8044_1104_8000_2441, 0x1023_0120_8204_4112, 0x0080_4080_200A_0012, 0x0004_2031_0A00_4A42,
+            0x0023_0010_0402_0801, 0x0882_0010_0804_0102, 0x0002_3008_8118_020C, 0x0000_0190_2504_0042,
+        ];
+
+        const BISHOP_MAGICS: [u64; 64] = [
+            0x1010_2202_0408_2A00, 0x80E0_0202_0200_2804, 0x2008_4801_0420_0020, 0x0002_2092_0280_002D,
+            0x3204_0421_000B_0284, 0x1002_0804_0400_0400, 0x0004_1608_9208_0040, 0x2203_0242_0620_4201,
+            0x0002_4042_6401_0200, 0x1120_9084_0842_8124, 0xB100_4244_0300_2280, 0x2400_0806_0440_C288,
+            0x2040_0404_2049_0400, 0x0100_6202_1004_0022, 0x0400_0841_0420_2028, 0x0010_0500_8090_8820,
+            0x0C90_A044_9082_4802, 0x0002_00A0_0821_0130, 0x0C08_0010_0020_4010, 0x0008_0001_8601_4480,
+            0x0601_0448_2008_0021, 0x0002_0001_0101_3100, 0x1400_A081_0808_0204, 0x0250_4011_0448_5410,
+            0x4820_2408_1014_2843, 0x0009_142A_2018_2200, 0x0848_1400_4844_0020, 0x2020_1200_0040_0440,
+            0x0108_8402_0080_2003, 0x0009_0700_8200_9492, 0x020C_0C00_3842_4245, 0xCA44_0058_0821_0410,
+            0x8011_2120_0050_0404, 0x2028_8405_1010_1008, 0x0004_042A_0004_1400, 0x0624_0200_8098_0080,
+            0x1820_4100_4084_0040, 0x2201_0042_0205_0100, 0x402A_088A_2404_0224, 0x0242_0610_4000_2400,
+            0x9002_0202_4008_21A0, 0x00C9_0090_04E0_1002, 0x58C2_0602_0202_3100, 0x0000_0122_1404_0800,
+            0x0210_8468_1010_0200, 0x0004_2080_8101_0200, 0x01A4_1084_0444_2100, 0x8054_082C_8028_0106,
+            0x0004_1449_0410_4208, 0x0032_4C0A_1110_4000, 0x1000_0202_3104_0100, 0x2080_0010_4202_0004,
+            0x0544_0210_2028_8104, 0x1103_5014_0808_3020, 0x4010_4510_0496_0002, 0x0030_1009_1C44_902C,
+            0x0102_4028_8420_2000, 0x0480_804C_0084_1086, 0x0460_2C86_0221_0400, 0x0000_0040_0042_0200,
+            0x0040_0000_2044_2C18, 0x4483_8040_8909_4100, 0x8000_0B02_4802_0400, 0x0045_0108_0800_8680,
+        ];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BitBoardPosition;
+    use crate::definitions::Square;
+    use deku::prelude::*;
+
+    #[test]
+    fn move_counters_stop_at_the_largest_fen_value() {
+        let mut position = BitBoardPosition::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 65535 65535").unwrap();
+        let undo = position.make_move((Square::E8, Square::D8), None).unwrap();
+        assert_eq!(position.to_fen(), "3k4/8/8/8/8/8/8/4K3 w - - 65535 65535");
+
+        position.unmake_move(undo);
+        assert_eq!(position.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 65535 65535");
+    }
+
+    #[test]
+    fn deku_rebuilds_the_derived_fields() {
+        let fens = [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w Kq - 3 12",
+            "rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3",
+        ];
+        for fen in fens {
+            let position = BitBoardPosition::from_fen(fen).unwrap();
+            let bytes = position.to_bytes().unwrap();
+            let ((rest, _), read) = BitBoardPosition::from_bytes((&bytes, 0)).unwrap();
+
+            assert!(rest.is_empty());
+            assert_eq!(read.zobrist_key(), position.zobrist_key(), "{}", fen);
+            assert_eq!(read.mailbox, position.mailbox, "{}", fen);
+            assert_eq!(read.player_occupancy, position.player_occupancy, "{}", fen);
+            assert_eq!(read.to_fen(), fen);
+        }
     }
 }
@@ -0,0 +1,151 @@
+use super::{
+    BitBoard,
+    BitBoardPosition,
+    Move,
+    Piece,
+    PieceType,
+    Player,
+    Rank,
+    Square,
+    calculations::{self, precalculations::attack_sets},
+};
+
+const PROMOTION_CHOICES: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+impl BitBoardPosition {
+    /// Every move the side to play can make, including castling, en passant
+    /// and one entry per promotion piece.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let mut moves = vec![];
+        let Some(king) = self.king_square(self.to_play) else { return moves; };
+        let own_pieces = self.player_occupancy(self.to_play);
+        let opponent_pieces = self.player_occupancy(self.to_play.opponent());
+        let all_pieces = self.occupancy();
+
+        // the king can't hide from a slider by stepping back along its line,
+        // so it's removed before looking at what the opponent attacks
+        let danger = calculations::attacked_squares(&self.board, self.to_play.opponent(), all_pieces & !king.to_u64());
+        let piece = Piece { piece_type: PieceType::King, owner: self.to_play };
+        for end in BitBoard(calculations::king_moves(king.to_u64()) & !own_pieces & !danger) {
+            moves.push(Move { start: king, end, piece, promotion: None });
+        }
+
+        let checkers = self.checkers();
+        if checkers.count_ones() > 1 { return moves; }
+
+        // in check, other pieces have to capture the checker or block it
+        let check_mask = match BitBoard(checkers).next() {
+            Some(checker) => checkers | attack_sets().between(king, checker),
+            None => u64::MAX,
+        };
+
+        let mut pin_rays = [u64::MAX; 64];
+        for pin in self.pins() {
+            pin_rays[pin.pinned as usize] = pin.ray;
+        }
+
+        for piece_type_determinant in 0..5 {
+            let piece_type = PieceType::try_from(piece_type_determinant as u8).unwrap();
+            let layer = self.board[piece_type_determinant + (6 * self.to_play as usize)];
+
+            for start in BitBoard(layer) {
+                let allowed = check_mask & pin_rays[start as usize];
+                let targets = match piece_type {
+                    PieceType::Pawn => {
+                        self.push_pawn_moves(&mut moves, start, all_pieces, opponent_pieces, allowed);
+                        continue;
+                    },
+                    PieceType::Knight => calculations::knight_attacks(start.to_u64()),
+                    _ => attack_sets().sliding_attacks(start, piece_type, all_pieces),
+                };
+
+                let piece = Piece { piece_type, owner: self.to_play };
+                for end in BitBoard(targets & !own_pieces & allowed) {
+                    moves.push(Move { start, end, piece, promotion: None });
+                }
+            }
+        }
+
+        if checkers == 0 {
+            self.push_castle_moves(&mut moves);
+        }
+
+        moves
+    }
+
+    fn push_pawn_moves(&self, moves: &mut Vec<Move>, start: Square, all_pieces: u64, opponent_pieces: u64, allowed: u64) {
+        let piece = Piece { piece_type: PieceType::Pawn, owner: self.to_play };
+        let (single_push, start_rank) = match self.to_play {
+            Player::White => (start.to_u64() << 8, Rank::Second),
+            Player::Black => (start.to_u64() >> 8, Rank::Seventh),
+        };
+
+        let mut targets = 0;
+        if single_push & all_pieces == 0 {
+            targets |= single_push;
+
+            if start.to_u64() & start_rank.to_u64() != 0 {
+                let double_push = match self.to_play {
+                    Player::White => single_push << 8,
+                    Player::Black => single_push >> 8,
+                };
+                if double_push & all_pieces == 0 {
+                    targets |= double_push;
+                }
+            }
+        }
+
+        targets |= calculations::pawn_attacks(start.to_u64(), self.to_play) & opponent_pieces;
+        targets &= allowed;
+
+        // en passant removes two pieces from the king's surroundings at once,
+        // which neither the pin rays nor the check mask account for
+        if self.en_passant_square != Square::Invalid
+            && calculations::pawn_attacks(start.to_u64(), self.to_play) & self.en_passant_square.to_u64() != 0 {
+            let en_passant = Move { start, end: self.en_passant_square, piece, promotion: None };
+            if !calculations::is_king_in_check(&self.board_after_move(&en_passant, false), self.to_play) {
+                moves.push(en_passant);
+            }
+        }
+
+        for end in BitBoard(targets) {
+            if end.to_u64() & (Rank::First.to_u64() | Rank::Eight.to_u64()) != 0 {
+                for promotion in PROMOTION_CHOICES {
+                    moves.push(Move { start, end, piece, promotion: Some(promotion) });
+                }
+            } else {
+                moves.push(Move { start, end, piece, promotion: None });
+            }
+        }
+    }
+
+    fn push_castle_moves(&self, moves: &mut Vec<Move>) {
+        let piece = Piece { piece_type: PieceType::King, owner: self.to_play };
+        let (start, king_side_castle, queen_side_castle) = match self.to_play {
+            Player::White => (Square::E1, self.white_king_side_castle, self.white_queen_side_castle),
+            Player::Black => (Square::E8, self.black_king_side_castle, self.black_queen_side_castle),
+        };
+
+        if self.board[PieceType::King as usize + (6 * self.to_play as usize)] & start.to_u64() == 0 { return; }
+
+        if king_side_castle {
+            let end = Square::try_from(start as u8 + 2).unwrap();
+            self.push_if_castle_allowed(moves, Move { start, end, piece, promotion: None });
+        }
+        if queen_side_castle {
+            let end = Square::try_from(start as u8 - 2).unwrap();
+            self.push_if_castle_allowed(moves, Move { start, end, piece, promotion: None });
+        }
+    }
+
+    fn push_if_castle_allowed(&self, moves: &mut Vec<Move>, castle_move: Move) {
+        if self.validate_castle_move(&castle_move).is_ok() {
+            moves.push(castle_move);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use super::{BitBoardPosition, Move};
+
+impl BitBoardPosition {
+    /// Counts the leaf nodes of the legal move tree `depth` plies deep.
+    pub fn perft(&self, depth: u32) -> u64 {
+        self.clone().perft_in_place(depth)
+    }
+
+    /// The perft count below each legal move, for narrowing down which
+    /// branch a wrong total comes from.
+    pub fn divide(&self, depth: u32) -> Vec<(Move, u64)> {
+        if depth == 0 { return vec![]; }
+
+        let mut position = self.clone();
+        position.legal_moves()
+            .into_iter()
+            .map(|legal_move| {
+                let undo = position.make_move((legal_move.start, legal_move.end), legal_move.promotion)
+                    .expect("move generator and make_move disagree");
+                let nodes = position.perft_in_place(depth - 1);
+                position.unmake_move(undo);
+                (legal_move, nodes)
+            })
+            .collect()
+    }
+
+    // every generated move goes through make_move rather than straight onto
+    // the board so a disagreement with the move validation fails loudly
+    fn perft_in_place(&mut self, depth: u32) -> u64 {
+        if depth == 0 { return 1; }
+
+        let legal_moves = self.legal_moves();
+        if depth == 1 { return legal_moves.len() as u64; }
+
+        let mut nodes = 0;
+        for legal_move in legal_moves {
+            let undo = self.make_move((legal_move.start, legal_move.end), legal_move.promotion)
+                .expect("move generator and make_move disagree");
+            nodes += self.perft_in_place(depth - 1);
+            self.unmake_move(undo);
+        }
+        nodes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BitBoardPosition;
+
+    fn position(fen: &str) -> BitBoardPosition {
+        BitBoardPosition::from_fen(fen).unwrap()
+    }
+
+    fn assert_perft(fen: &str, expected: &[u64]) {
+        let position = position(fen);
+        for (depth, nodes) in expected.iter().enumerate() {
+            assert_eq!(position.perft(depth as u32 + 1), *nodes, "{} at depth {}", fen, depth + 1);
+        }
+    }
+
+    #[test]
+    fn initial_position() {
+        assert_perft("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1", &[20, 400, 8902, 197281]);
+    }
+
+    #[test]
+    fn kiwipete() {
+        assert_perft("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862]);
+    }
+
+    #[test]
+    fn rook_and_pawn_endgame() {
+        assert_perft("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238]);
+    }
+
+    #[test]
+    fn promotions_and_castling_rights() {
+        assert_perft("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467]);
+        assert_perft("r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1", &[6, 264, 9467]);
+    }
+
+    #[test]
+    fn discovered_checks() {
+        assert_perft("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379]);
+        assert_perft("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10", &[46, 2079, 89890]);
+    }
+
+    #[test]
+    fn en_passant_edge_cases() {
+        // en passant must not expose the king along the rank or diagonal
+        assert_perft("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", &[18, 92, 1670, 10138]);
+        assert_perft("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", &[15, 126, 1928, 13931]);
+        assert_perft("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", &[13, 102, 1266, 10276]);
+    }
+
+    #[test]
+    fn castling_edge_cases() {
+        assert_perft("5k2/8/8/8/8/8/8/4K2R w K - 0 1", &[15, 66, 1198, 6399]);
+        assert_perft("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", &[16, 71, 1286, 7418]);
+        assert_perft("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", &[26, 1141, 27826]);
+        assert_perft("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", &[44, 1494, 50509]);
+    }
+
+    #[test]
+    fn promotion_edge_cases() {
+        assert_perft("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", &[11, 133, 1442, 19174]);
+        assert_perft("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", &[9, 40, 472, 2661]);
+        assert_perft("8/P1k5/K7/8/8/8/8/8 w - - 0 1", &[6, 27, 273, 1329]);
+        assert_perft("K1k5/8/P7/8/8/8/8/8 w - - 0 1", &[2, 6, 13, 63]);
+        assert_perft("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", &[10, 25, 268, 926]);
+        assert_perft("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", &[29, 165, 5160, 31961]);
+        assert_perft("8/8/2k5/5q2/5n2/8/5K2/8 b - - 0 1", &[37, 183, 6559, 23527]);
+    }
+
+    #[test]
+    fn divide_sums_to_perft() {
+        let position = position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        let divided = position.divide(2);
+        assert_eq!(divided.len(), 48);
+        assert_eq!(divided.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+    }
+}
@@ -0,0 +1,98 @@
+use super::{
+    BitBoard,
+    BitBoardPosition,
+    Move,
+    PieceType,
+    calculations,
+    captured_square,
+};
+
+// least valuable attacker first
+const EXCHANGE_ORDER: [PieceType; 6] = [
+    PieceType::Pawn,
+    PieceType::Knight,
+    PieceType::Bishop,
+    PieceType::Rook,
+    PieceType::Queen,
+    PieceType::King,
+];
+
+impl BitBoardPosition {
+    /// Static exchange evaluation: the material the player to move comes out
+    /// ahead (or behind, if negative) after `capture` and every recapture on
+    /// its square, each side always recapturing with its least valuable piece
+    /// and free to stop once continuing would lose material. Sliders lined up
+    /// behind other attackers join in once the piece in front has captured.
+    /// Pins are not taken into account.
+    pub fn static_exchange(&self, capture: &Move) -> i32 {
+        let target = capture.end;
+        let mut occupancy = self.occupancy()
+            & !capture.start.to_u64()
+            & !captured_square(capture, self.en_passant_square).to_u64();
+
+        let mut gains = vec![self.captured_piece(capture).map_or(0, |piece| piece.piece_type.value())];
+        let mut on_target = capture.piece.piece_type.value();
+        if let Some(promotion) = capture.promotion {
+            gains[0] += promotion.value() - PieceType::Pawn.value();
+            on_target = promotion.value();
+        }
+
+        let mut side = self.to_play.opponent();
+        loop {
+            let attackers = calculations::attackers_to(target, &self.board, side, occupancy) & occupancy;
+            let Some((piece_type, attacker)) = EXCHANGE_ORDER.iter().find_map(|piece_type| {
+                let layer = self.board[*piece_type as usize + (6 * side as usize)];
+                BitBoard(attackers & layer).next().map(|square| (*piece_type, square))
+            }) else { break; };
+
+            // the king may only take last
+            if piece_type == PieceType::King
+                && calculations::attackers_to(target, &self.board, side.opponent(), occupancy) & occupancy != 0 {
+                break;
+            }
+
+            gains.push(on_target - gains[gains.len() - 1]);
+            occupancy &= !attacker.to_u64();
+            on_target = piece_type.value();
+            side = side.opponent();
+        }
+
+        // each side only recaptures if it doesn't leave them worse off than stopping
+        while gains.len() > 1 {
+            let reply = gains.pop().unwrap();
+            let last = gains.len() - 1;
+            gains[last] = -(-gains[last]).max(reply);
+        }
+        gains[0]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitBoardPosition, Move};
+
+    fn exchange(fen: &str, san: &str) -> i32 {
+        let position = BitBoardPosition::from_fen(fen).unwrap();
+        position.static_exchange(&Move::from_san(san, &position).unwrap())
+    }
+
+    #[test]
+    fn winning_and_losing_captures() {
+        assert_eq!(exchange("4k3/8/8/3n4/8/8/8/3RK3 w - - 0 1", "Rxd5"), 320);
+        assert_eq!(exchange("4k3/2p5/3p4/8/8/8/8/3QK3 w - - 0 1", "Qxd6"), 100 - 900);
+        assert_eq!(exchange("4k3/3n4/8/4p3/8/8/4R3/4K3 w - - 0 1", "Rxe5"), 100 - 500);
+    }
+
+    #[test]
+    fn x_ray() {
+        // Rd1 backs up Rd2 once it has captured
+        assert_eq!(exchange("3rk3/8/8/3p4/8/8/3R4/3RK3 w - - 0 1", "Rxd5"), 100);
+        assert_eq!(exchange("3rk3/8/8/3p4/8/8/3R4/4K3 w - - 0 1", "Rxd5"), 100 - 500);
+    }
+
+    #[test]
+    fn king_recaptures_last() {
+        assert_eq!(exchange("8/8/4k3/3p4/8/8/B7/4K3 w - - 0 1", "Bxd5+"), 100 - 330);
+        assert_eq!(exchange("8/8/4k3/3p4/8/8/B7/3RK3 w - - 0 1", "Bxd5+"), 100);
+    }
+}
@@ -0,0 +1,132 @@
+use crate::definitions::PositionError;
+
+use super::{
+    BitBoard,
+    BitBoardPosition,
+    PieceType,
+    Player,
+    Rank,
+    Square,
+    calculations,
+};
+
+impl BitBoardPosition {
+    /// Checks a position that didn't come from playing moves, such as one
+    /// loaded from a user or a file, and reports every problem found.
+    pub fn validate(&self) -> Result<(), Vec<PositionError>> {
+        let mut errors = vec![];
+
+        let mut seen = 0;
+        for layer in self.board {
+            for square in BitBoard(layer & seen) {
+                errors.push(PositionError::OverlappingPieces(square));
+            }
+            seen |= layer;
+        }
+
+        for player in [Player::White, Player::Black] {
+            match self.board[PieceType::King as usize + (6 * player as usize)].count_ones() {
+                0 => errors.push(PositionError::MissingKing(player)),
+                1 => (),
+                _ => errors.push(PositionError::TooManyKings(player)),
+            }
+        }
+
+        let pawns = self.board[PieceType::Pawn as usize] | self.board[PieceType::Pawn as usize + 6];
+        for square in BitBoard(pawns & (Rank::First.to_u64() | Rank::Eight.to_u64())) {
+            errors.push(PositionError::PawnOnBackRank(square));
+        }
+
+        let castling_rights = [
+            (self.white_king_side_castle, Player::White, Square::E1, Square::H1),
+            (self.white_queen_side_castle, Player::White, Square::E1, Square::A1),
+            (self.black_king_side_castle, Player::Black, Square::E8, Square::H8),
+            (self.black_queen_side_castle, Player::Black, Square::E8, Square::A8),
+        ];
+        for (has_right, player, king, rook) in castling_rights {
+            if has_right && !(self.has_piece(PieceType::King, player, king) && self.has_piece(PieceType::Rook, player, rook)) {
+                errors.push(PositionError::InvalidCastlingRight(rook));
+            }
+        }
+
+        if self.en_passant_square != Square::Invalid && !self.is_possible_en_passant_square() {
+            errors.push(PositionError::InvalidEnPassantSquare(self.en_passant_square));
+        }
+
+        let opponent = self.to_play.opponent();
+        if self.board[PieceType::King as usize + (6 * opponent as usize)].count_ones() == 1
+            && calculations::is_king_in_check(&self.board, opponent) {
+            errors.push(PositionError::OpponentInCheck);
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors) }
+    }
+
+    fn has_piece(&self, piece_type: PieceType, player: Player, square: Square) -> bool {
+        self.board[piece_type as usize + (6 * player as usize)] & square.to_u64() != 0
+    }
+
+    // the opponent's pawn has to stand right past the empty square it
+    // skipped, with the square it started from empty as well
+    fn is_possible_en_passant_square(&self) -> bool {
+        let skipped = self.en_passant_square.to_u64();
+        let (rank, pawn, start) = match self.to_play {
+            Player::White => (Rank::Sixth, skipped >> 8, skipped << 8),
+            Player::Black => (Rank::Third, skipped << 8, skipped >> 8),
+        };
+        let opponent_pawns = self.board[PieceType::Pawn as usize + (6 * self.to_play.opponent() as usize)];
+
+        skipped & rank.to_u64() != 0
+            && pawn & opponent_pawns != 0
+            && (skipped | start) & self.occupancy() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BitBoardPosition, PieceType, PositionError, Player, Square};
+
+    fn errors(fen: &str) -> Vec<PositionError> {
+        BitBoardPosition::from_fen(fen).unwrap().validate().err().unwrap_or_default()
+    }
+
+    #[test]
+    fn valid() {
+        assert_eq!(errors("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), vec![]);
+        assert_eq!(errors("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3"), vec![]);
+    }
+
+    #[test]
+    fn kings() {
+        assert_eq!(errors("8/8/8/8/8/8/8/4K3 w - - 0 1"), vec![PositionError::MissingKing(Player::Black)]);
+        assert_eq!(errors("4k3/8/8/8/8/8/8/K6K w - - 0 1"), vec![PositionError::TooManyKings(Player::White)]);
+        assert_eq!(errors("4k3/8/8/8/8/8/8/4K2r w - - 0 1"), vec![]);
+        assert_eq!(errors("4k3/8/8/8/8/8/8/4K2r b - - 0 1"), vec![PositionError::OpponentInCheck]);
+    }
+
+    #[test]
+    fn pieces() {
+        assert_eq!(errors("P3k3/8/8/8/8/8/8/4K2p w - - 0 1"), vec![PositionError::PawnOnBackRank(Square::H1), PositionError::PawnOnBackRank(Square::A8)]);
+
+        let mut overlapping = BitBoardPosition::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        overlapping.board[PieceType::Knight as usize] |= Square::E1.to_u64();
+        assert_eq!(overlapping.validate(), Err(vec![PositionError::OverlappingPieces(Square::E1)]));
+    }
+
+    #[test]
+    fn castling_rights() {
+        assert_eq!(errors("r3k3/8/8/8/8/8/8/4K2R w Kq - 0 1"), vec![]);
+        assert_eq!(errors("r3k3/8/8/8/8/8/8/3K3R w KQkq - 0 1"), vec![
+            PositionError::InvalidCastlingRight(Square::H1),
+            PositionError::InvalidCastlingRight(Square::A1),
+            PositionError::InvalidCastlingRight(Square::H8),
+        ]);
+    }
+
+    #[test]
+    fn en_passant_square() {
+        assert_eq!(errors("4k3/8/8/8/8/8/8/4K3 b - e3 0 1"), vec![PositionError::InvalidEnPassantSquare(Square::E3)]);
+        assert_eq!(errors("4k3/8/8/8/4P3/8/8/4K3 w - e3 0 1"), vec![PositionError::InvalidEnPassantSquare(Square::E3)]);
+        assert_eq!(errors("4k3/8/8/8/4P3/4N3/8/4K3 b - e3 0 1"), vec![PositionError::InvalidEnPassantSquare(Square::E3)]);
+    }
+}
@@ -0,0 +1,214 @@
+use std::hash::{Hash, Hasher};
+
+use super::{
+    BitBoard,
+    BitBoardPosition,
+    PieceType,
+    Player,
+    Square,
+};
+
+struct ZobristKeys {
+    pieces: [[u64; 64]; 12],
+    black_to_play: u64,
+    castling: [u64; 4],
+    en_passant_files: [u64; 8],
+}
+
+// generated at compile time so keys are identical across builds and platforms,
+// which position databases keyed on them rely on
+const KEYS: ZobristKeys = generate_keys();
+
+const fn split_mix(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut value = state;
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    (state, value ^ (value >> 31))
+}
+
+const fn generate_keys() -> ZobristKeys {
+    let mut keys = ZobristKeys {
+        pieces: [[0; 64]; 12],
+        black_to_play: 0,
+        castling: [0; 4],
+        en_passant_files: [0; 8],
+    };
+    let mut state = 0x5D5F_C4E5_5D1C_E5A1;
+    let mut value;
+
+    let mut layer = 0;
+    while layer < 12 {
+        let mut square_index = 0;
+        while square_index < 64 {
+            (state, value) = split_mix(state);
+            keys.pieces[layer][square_index] = value;
+            square_index += 1;
+        }
+        layer += 1;
+    }
+
+    (state, value) = split_mix(state);
+    keys.black_to_play = value;
+
+    let mut index = 0;
+    while index < 4 {
+        (state, value) = split_mix(state);
+        keys.castling[index] = value;
+        index += 1;
+    }
+
+    let mut index = 0;
+    while index < 8 {
+        (state, value) = split_mix(state);
+        keys.en_passant_files[index] = value;
+        index += 1;
+    }
+
+    keys
+}
+
+pub(super) fn piece_key(layer: usize, square: Square) -> u64 {
+    KEYS.pieces[layer][square as usize]
+}
+
+pub(super) fn side_key() -> u64 {
+    KEYS.black_to_play
+}
+
+pub(super) fn castling_key(white_king_side: bool, white_queen_side: bool, black_king_side: bool, black_queen_side: bool) -> u64 {
+    [white_king_side, white_queen_side, black_king_side, black_queen_side]
+        .iter()
+        .zip(KEYS.castling.iter())
+        .filter(|(has_right, _)| **has_right)
+        .fold(0, |acc, (_, key)| acc ^ key)
+}
+
+pub(super) fn en_passant_key(en_passant_square: Square) -> u64 {
+    if en_passant_square == Square::Invalid { return 0; }
+    KEYS.en_passant_files[en_passant_square as usize % 8]
+}
+
+impl BitBoardPosition {
+    /// 64-bit Zobrist key over piece placement, side to play, castling rights
+    /// and en passant file, when the capture is possible. Kept up to date by
+    /// every move, so it's free to read.
+    pub fn zobrist_key(&self) -> u64 {
+        self.hash
+    }
+
+    pub(super) fn compute_zobrist_key(&self) -> u64 {
+        let castling = [
+            self.white_king_side_castle,
+            self.white_queen_side_castle,
+            self.black_king_side_castle,
+            self.black_queen_side_castle,
+        ];
+        position_key(&self.board, self.to_play, castling, self.en_passant_square)
+    }
+
+    /// The en passant square when a pawn of the side to move stands next to
+    /// the pawn that just moved, `Square::Invalid` otherwise. Positions only
+    /// differ by en passant when the capture could be made.
+    pub(super) fn capturable_en_passant_square(&self) -> Square {
+        capturable_en_passant_square(&self.board, self.to_play, self.en_passant_square)
+    }
+}
+
+/// The full key computed from the bitboards alone, which is all deku has to
+/// go on when reading a position.
+pub(super) fn position_key(board: &[u64; 12], to_play: Player, castling: [bool; 4], en_passant_square: Square) -> u64 {
+    let mut key = 0;
+
+    for (layer, pieces) in board.iter().enumerate() {
+        for square in BitBoard(*pieces) {
+            key ^= piece_key(layer, square);
+        }
+    }
+
+    if to_play == Player::Black {
+        key ^= side_key();
+    }
+
+    let [white_king_side, white_queen_side, black_king_side, black_queen_side] = castling;
+    key ^ castling_key(white_king_side, white_queen_side, black_king_side, black_queen_side)
+        ^ en_passant_key(capturable_en_passant_square(board, to_play, en_passant_square))
+}
+
+fn capturable_en_passant_square(board: &[u64; 12], to_play: Player, square: Square) -> Square {
+    if square == Square::Invalid { return Square::Invalid; }
+
+    let rank_offset = match to_play {
+        Player::White => -1,
+        Player::Black => 1,
+    };
+    let pawns = board[PieceType::Pawn as usize + (6 * to_play as usize)];
+    let can_capture = [-1, 1].into_iter().any(|file_offset| {
+        square.offset(file_offset, rank_offset).is_some_and(|start| pawns & start.to_u64() != 0)
+    });
+    if can_capture { square } else { Square::Invalid }
+}
+
+impl PartialEq for BitBoardPosition {
+    /// Positions are equal when they'd be treated as a repetition, the move
+    /// clocks are ignored.
+    fn eq(&self, other: &Self) -> bool {
+        self.hash == other.hash
+            && self.board == other.board
+            && self.to_play == other.to_play
+            && self.capturable_en_passant_square() == other.capturable_en_passant_square()
+            && self.white_king_side_castle == other.white_king_side_castle
+            && self.white_queen_side_castle == other.white_queen_side_castle
+            && self.black_king_side_castle == other.black_king_side_castle
+            && self.black_queen_side_castle == other.black_queen_side_castle
+    }
+}
+
+impl Eq for BitBoardPosition {}
+
+impl Hash for BitBoardPosition {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.hash.hash(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bit_board::BitBoardPosition;
+    use crate::definitions::{DrawReason, Move, INITIAL_POSITION};
+
+    fn play(position: &mut BitBoardPosition, history: &mut Vec<u64>, moves: &str) {
+        for san in moves.split_whitespace() {
+            let played_move = Move::from_san(san, position).unwrap();
+            position.make_move((played_move.start, played_move.end), played_move.promotion).unwrap();
+            history.push(position.zobrist_key());
+        }
+    }
+
+    #[test]
+    fn en_passant_only_counts_when_it_can_be_taken() {
+        let mut position = BitBoardPosition::from_position(&INITIAL_POSITION);
+        let mut history = vec![position.zobrist_key()];
+        play(&mut position, &mut history, "e4 Nf6 Nf3 Ng8 Ng1 Nf6 Nf3 Ng8 Ng1");
+        assert_eq!(position.repetition_count(&history), 3);
+        assert_eq!(position.claimable_draw(&history), Some(DrawReason::ThreefoldRepetition));
+
+        // the black pawn on d4 could take e3, so the first visit is a different position
+        let start = "rnbqkbnr/ppp1pppp/8/8/3p4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        let mut position = BitBoardPosition::from_fen(start).unwrap();
+        let mut history = vec![position.zobrist_key()];
+        play(&mut position, &mut history, "e4 Nf6 Nf3 Ng8 Ng1 Nf6 Nf3 Ng8 Ng1");
+        assert_eq!(position.repetition_count(&history), 2);
+        assert_eq!(position.claimable_draw(&history), None);
+    }
+
+    #[test]
+    fn incremental_keys_match_a_full_computation() {
+        let mut position = BitBoardPosition::from_fen("rnbqkbnr/ppp1pppp/8/8/3p4/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1").unwrap();
+        let mut history = vec![];
+        for san in ["e4", "Nf6", "c4", "dxc3", "a4", "Nd5", "b4", "e5", "b5", "c5", "bxc6"] {
+            play(&mut position, &mut history, san);
+            assert_eq!(position.zobrist_key(), BitBoardPosition::from_fen(&position.to_fen()).unwrap().zobrist_key(), "after {}", san);
+        }
+    }
+}
@@ -1,8 +1,12 @@
 use std::str::FromStr;
 use std::result::Result;
+use std::fmt;
 
 use deku::prelude::*;
 use num_enum::TryFromPrimitive;
+use serde::{Deserialize, Serialize};
+
+mod serialization;
 
 pub static INITIAL_POSITION: SimplePosition = SimplePosition {
     to_play: Player::White,
@@ -11,7 +15,8 @@ pub static INITIAL_POSITION: SimplePosition = SimplePosition {
     white_queen_side_castle: true,
     black_king_side_castle: true,
     black_queen_side_castle: true,
-    promotion_square: Square::Invalid,
+    halfmove_clock: 0,
+    fullmove_number: 1,
     board: [
         Occupant::Piece(Piece { piece_type: PieceType::Rook, owner: Player::White }),
         Occupant::Piece(Piece { piece_type: PieceType::Knight, owner: Player::White }),
@@ -60,6 +65,7 @@ pub static INITIAL_POSITION: SimplePosition = SimplePosition {
         ]
 };
 
+/// Serialized as the lowercase name, `"e4"`, and `Invalid` as `null`.
 #[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, TryFromPrimitive, DekuRead, DekuWrite)]
 #[deku(type = "u8")]
 #[repr(u8)]
@@ -78,14 +84,12 @@ pub enum Square {
 
 #[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, TryFromPrimitive)]
 #[repr(u8)]
-#[allow(dead_code)]
 pub enum File {
     A,B,C,D,E,F,G,H
 }
 
 #[derive(PartialEq, Eq, Hash, Debug, Copy, Clone, TryFromPrimitive)]
 #[repr(u8)]
-#[allow(dead_code)]
 pub enum Rank {
     First,
     Second,
@@ -97,24 +101,111 @@ pub enum Rank {
     Eight,
 }
 
+impl Square {
+    pub fn from_coordinates(file: File, rank: Rank) -> Self {
+        Square::try_from(rank as u8 * 8 + file as u8).unwrap()
+    }
+
+    /// The file the square is on, `File::A` for A1 to A8.
+    ///
+    /// # Panics
+    ///
+    /// On `Square::Invalid`, which isn't on the board.
+    pub fn file(self) -> File {
+        assert!(self != Square::Invalid, "Square::Invalid has no file");
+        File::try_from(self as u8 % 8).unwrap()
+    }
+
+    /// The rank the square is on, `Rank::First` for A1 to H1.
+    ///
+    /// # Panics
+    ///
+    /// On `Square::Invalid`, which isn't on the board.
+    pub fn rank(self) -> Rank {
+        assert!(self != Square::Invalid, "Square::Invalid has no rank");
+        Rank::try_from(self as u8 / 8).unwrap()
+    }
+
+    /// The square `file_offset` files to the right and `rank_offset` ranks
+    /// up, as White sees the board, or `None` past the edge.
+    pub fn offset(self, file_offset: i8, rank_offset: i8) -> Option<Self> {
+        if self == Square::Invalid { return None; }
+        let file = self.file() as i8 + file_offset;
+        let rank = self.rank() as i8 + rank_offset;
+        if !(0..8).contains(&file) || !(0..8).contains(&rank) { return None; }
+        Some(Square::from_coordinates(File::try_from(file as u8).unwrap(), Rank::try_from(rank as u8).unwrap()))
+    }
+
+    /// A1 to H8, a rank at a time, without `Square::Invalid`.
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..64).map(|index| Square::try_from(index).unwrap())
+    }
+}
+
+impl File {
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..8).map(|index| File::try_from(index).unwrap())
+    }
+
+    /// Either case, `'a'` to `'h'`.
+    pub fn from_char(symbol: char) -> Option<Self> {
+        match symbol.to_ascii_lowercase() {
+            symbol @ 'a'..='h' => File::try_from(symbol as u8 - b'a').ok(),
+            _ => None,
+        }
+    }
+}
+
+impl Rank {
+    pub fn all() -> impl Iterator<Item = Self> {
+        (0..8).map(|index| Rank::try_from(index).unwrap())
+    }
+
+    /// `'1'` to `'8'`.
+    pub fn from_char(symbol: char) -> Option<Self> {
+        match symbol {
+            '1'..='8' => Rank::try_from(symbol as u8 - b'1').ok(),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", (b'a' + *self as u8) as char)
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", *self as u8 + 1)
+    }
+}
+
+/// The lowercase name, `e4`, and `-` for `Square::Invalid` as in FEN.
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Square::Invalid => write!(f, "-"),
+            square => write!(f, "{}{}", square.file(), square.rank()),
+        }
+    }
+}
+
+/// Accepts the name in either case, `e4` or `E4`.
 impl FromStr for Square {
     type Err = &'static str;
     fn from_str(s: &str) -> Result<Self, &'static str> {
-            match s {
-                "A1" => Ok(Square::A1), "A2" => Ok(Square::A2), "A3" => Ok(Square::A3), "A4" => Ok(Square::A4), "A5" => Ok(Square::A5), "A6" => Ok(Square::A6), "A7" => Ok(Square::A7), "A8" => Ok(Square::A8),
-                "B1" => Ok(Square::B1), "B2" => Ok(Square::B2), "B3" => Ok(Square::B3), "B4" => Ok(Square::B4), "B5" => Ok(Square::B5), "B6" => Ok(Square::B6), "B7" => Ok(Square::B7), "B8" => Ok(Square::B8),
-                "C1" => Ok(Square::C1), "C2" => Ok(Square::C2), "C3" => Ok(Square::C3), "C4" => Ok(Square::C4), "C5" => Ok(Square::C5), "C6" => Ok(Square::C6), "C7" => Ok(Square::C7), "C8" => Ok(Square::C8),
-                "D1" => Ok(Square::D1), "D2" => Ok(Square::D2), "D3" => Ok(Square::D3), "D4" => Ok(Square::D4), "D5" => Ok(Square::D5), "D6" => Ok(Square::D6), "D7" => Ok(Square::D7), "D8" => Ok(Square::D8),
-                "E1" => Ok(Square::E1), "E2" => Ok(Square::E2), "E3" => Ok(Square::E3), "E4" => Ok(Square::E4), "E5" => Ok(Square::E5), "E6" => Ok(Square::E6), "E7" => Ok(Square::E7), "E8" => Ok(Square::E8),
-                "F1" => Ok(Square::F1), "F2" => Ok(Square::F2), "F3" => Ok(Square::F3), "F4" => Ok(Square::F4), "F5" => Ok(Square::F5), "F6" => Ok(Square::F6), "F7" => Ok(Square::F7), "F8" => Ok(Square::F8),
-                "G1" => Ok(Square::G1), "G2" => Ok(Square::G2), "G3" => Ok(Square::G3), "G4" => Ok(Square::G4), "G5" => Ok(Square::G5), "G6" => Ok(Square::G6), "G7" => Ok(Square::G7), "G8" => Ok(Square::G8),
-                "H1" => Ok(Square::H1), "H2" => Ok(Square::H2), "H3" => Ok(Square::H3), "H4" => Ok(Square::H4), "H5" => Ok(Square::H5), "H6" => Ok(Square::H6), "H7" => Ok(Square::H7), "H8" => Ok(Square::H8),
-                _ => Err("Invalid square name")
-            }
+        let mut symbols = s.chars();
+        match (symbols.next().and_then(File::from_char), symbols.next().and_then(Rank::from_char), symbols.next()) {
+            (Some(file), Some(rank), None) => Ok(Square::from_coordinates(file, rank)),
+            _ => Err("Invalid square name"),
+        }
     }
 }
 
-#[derive(Clone, Debug, Copy, PartialEq, TryFromPrimitive)]
+#[derive(Clone, Debug, Copy, PartialEq, TryFromPrimitive, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 #[repr(u8)]
 pub enum PieceType {
     Pawn,
@@ -125,38 +216,57 @@ pub enum PieceType {
     King,
 }
 
+impl PieceType {
+    /// Material value in centipawns. The king is worth more than everything
+    /// else on the board combined so trading it never looks good.
+    pub fn value(&self) -> i32 {
+        match self {
+            PieceType::Pawn => 100,
+            PieceType::Knight => 320,
+            PieceType::Bishop => 330,
+            PieceType::Rook => 500,
+            PieceType::Queen => 900,
+            PieceType::King => 20_000,
+        }
+    }
+}
 
-#[derive(Clone, Debug, Copy)]
+
+/// Serialized as `{"type":"knight","owner":"white"}`.
+#[derive(Clone, Debug, Copy, PartialEq, Serialize, Deserialize)]
 pub struct Piece {
+    #[serde(rename = "type")]
     pub piece_type: PieceType,
     pub owner: Player,
 }
 
+/// Outlined glyphs for White and filled ones for Black, as Unicode names them.
 impl ToString for Piece {
     fn to_string(&self) -> String {
         if self.owner == Player::White {
             return match self.piece_type {
-                PieceType::Pawn => "♟",
-                PieceType::Rook => "♜",
-                PieceType::Knight => "♞",
-                PieceType::Bishop => "♝",
-                PieceType::Queen => "♛",
-                PieceType::King => "♚",
+                PieceType::Pawn => "♙",
+                PieceType::Rook => "♖",
+                PieceType::Knight => "♘",
+                PieceType::Bishop => "♗",
+                PieceType::Queen => "♕",
+                PieceType::King => "♔",
             }.to_string()
         }
 
         match self.piece_type {
-            PieceType::Pawn => "♙",
-            PieceType::Rook => "♖",
-            PieceType::Knight => "♘",
-            PieceType::Bishop => "♗",
-            PieceType::Queen => "♕",
-            PieceType::King => "♔",
+            PieceType::Pawn => "♟",
+            PieceType::Rook => "♜",
+            PieceType::Knight => "♞",
+            PieceType::Bishop => "♝",
+            PieceType::Queen => "♛",
+            PieceType::King => "♚",
         }.to_string()
 
     }
 }
 
+/// Serialized as the piece, or `null` for an empty square.
 #[derive(Clone, Debug, Copy)]
 pub enum Occupant {
     None,
@@ -173,8 +283,9 @@ impl Occupant {
 }
 
 
-#[derive(Clone, Debug, Copy, PartialEq, Eq, DekuRead, DekuWrite)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq, DekuRead, DekuWrite, Serialize, Deserialize)]
 #[deku(type = "u8")]
+#[serde(rename_all = "lowercase")]
 pub enum Player {
     White = 0,
     Black,
@@ -189,8 +300,32 @@ impl Player {
     }
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate(Player),
+    Stalemate,
+    Draw(DrawReason),
+}
+
+#[derive(Clone, Debug, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    /// Claimable once fifty moves by each side pass without a capture or pawn move
+    FiftyMoveRule,
+    /// Automatic after seventy-five moves by each side
+    SeventyFiveMoveRule,
+    /// Claimable when the same position occurs a third time
+    ThreefoldRepetition,
+    /// Automatic when the same position occurs a fifth time
+    FivefoldRepetition,
+    InsufficientMaterial,
+}
+
+/// Serialized with `board` as an array of the 64 occupants from a1 to h8
+/// and `en_passant_square` as `null` when there is none.
+#[derive(Debug, Serialize, Deserialize)]
 pub struct SimplePosition {
+    #[serde(with = "serialization::board")]
     pub board: [Occupant; 64],
     pub to_play: Player,
     pub en_passant_square: Square,
@@ -198,37 +333,137 @@ pub struct SimplePosition {
     pub white_queen_side_castle: bool,
     pub black_king_side_castle: bool,
     pub black_queen_side_castle: bool,
-    pub promotion_square: Square,
-}
-
-impl ToString for SimplePosition {
-    fn to_string(&self) -> String {
-        let mut result = String::new();
-        let mut i = 0;
-        let squares = self.board;
-        while i < squares.len() {
-            result.push_str(
-                &match squares[i] {
-                    Occupant::None => "-".to_owned(),
-                    Occupant::Piece(piece) => piece.to_string(),
-                }
-            );
-            i = i + 1;
-            if i % 8 == 0 {
-                result.push_str("\n");
-            }
-        }
-        result.push_str("\n\n");
-        result.push_str(match self.to_play { Player::White => "White", Player::Black => "Black" });
-        result.push_str(" to play");
-        result.push_str("\n\n");
-        result
-    }
+    pub halfmove_clock: u16,
+    pub fullmove_number: u16,
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Move {
     pub start: Square,
     pub end: Square,
     pub piece: Piece,
+    pub promotion: Option<PieceType>,
+}
+
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub enum MoveError {
+    InvalidSquare,
+    EmptySquare,
+    NotYourPiece,
+    OccupiedByOwnPiece,
+    IllegalPieceMove(PieceType),
+    BlockedPath,
+    PawnCaptureWithoutTarget,
+    PawnCannotCaptureForward,
+    LeavesKingInCheck,
+    CastlingNotAllowed,
+    CastlingBlocked,
+    CastlingOutOfCheck,
+    CastlingThroughCheck,
+    PromotionRequired,
+    UnexpectedPromotion,
+    InvalidPromotionPiece(PieceType),
+}
+
+impl fmt::Display for MoveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSquare => write!(f, "Invalid square"),
+            Self::EmptySquare => write!(f, "Square is empty"),
+            Self::NotYourPiece => write!(f, "Player doesn't own this piece"),
+            Self::OccupiedByOwnPiece => write!(f, "Player already has a piece occupying the end square"),
+            Self::IllegalPieceMove(piece_type) => write!(f, "Illegal {:?} move", piece_type),
+            Self::BlockedPath => write!(f, "Another piece is in the way"),
+            Self::PawnCaptureWithoutTarget => write!(f, "Diagonal pawn moves need to be a capture"),
+            Self::PawnCannotCaptureForward => write!(f, "Pawn cannot attack forward"),
+            Self::LeavesKingInCheck => write!(f, "King can't be in check"),
+            Self::CastlingNotAllowed => write!(f, "Castling rights have been lost on that side"),
+            Self::CastlingBlocked => write!(f, "Squares between the king and rook need to be empty"),
+            Self::CastlingOutOfCheck => write!(f, "Can't castle out of check"),
+            Self::CastlingThroughCheck => write!(f, "Can't castle through or into check"),
+            Self::PromotionRequired => write!(f, "A pawn reaching the last rank needs a piece to promote to"),
+            Self::UnexpectedPromotion => write!(f, "Only pawns reaching the last rank can promote"),
+            Self::InvalidPromotionPiece(piece_type) => write!(f, "Pawns can't promote to {:?}", piece_type),
+        }
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// Something about a position that can't come up in a legal game.
+#[derive(Clone, Debug, Copy, PartialEq)]
+pub enum PositionError {
+    OverlappingPieces(Square),
+    MissingKing(Player),
+    TooManyKings(Player),
+    PawnOnBackRank(Square),
+    /// The rook's starting square the right refers to.
+    InvalidCastlingRight(Square),
+    InvalidEnPassantSquare(Square),
+    OpponentInCheck,
+}
+
+impl fmt::Display for PositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OverlappingPieces(square) => write!(f, "More than one piece stands on {}", square),
+            Self::MissingKing(player) => write!(f, "{:?} has no king", player),
+            Self::TooManyKings(player) => write!(f, "{:?} has more than one king", player),
+            Self::PawnOnBackRank(square) => write!(f, "Pawn on {} can't stand on the first or last rank", square),
+            Self::InvalidCastlingRight(square) => write!(f, "Castling with the rook on {} needs it and the king on their starting squares", square),
+            Self::InvalidEnPassantSquare(square) => write!(f, "No pawn can have just skipped over {}", square),
+            Self::OpponentInCheck => write!(f, "The player who just moved can't be in check"),
+        }
+    }
+}
+
+impl std::error::Error for PositionError {}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{File, Rank, Square};
+
+    #[test]
+    fn coordinates() {
+        assert_eq!(Square::from_coordinates(File::A, Rank::First), Square::A1);
+        assert_eq!(Square::from_coordinates(File::E, Rank::Fourth), Square::E4);
+        assert_eq!(Square::from_coordinates(File::H, Rank::Eight), Square::H8);
+        for square in Square::all() {
+            assert_eq!(Square::from_coordinates(square.file(), square.rank()), square);
+        }
+        assert_eq!(Square::all().count(), 64);
+    }
+
+    #[test]
+    #[should_panic(expected = "Square::Invalid has no file")]
+    fn invalid_square_has_no_file() {
+        Square::Invalid.file();
+    }
+
+    #[test]
+    fn offsets() {
+        assert_eq!(Square::E4.offset(0, 0), Some(Square::E4));
+        assert_eq!(Square::E4.offset(1, 2), Some(Square::F6));
+        assert_eq!(Square::E4.offset(-4, -3), Some(Square::A1));
+        assert_eq!(Square::A1.offset(-1, 0), None);
+        assert_eq!(Square::H4.offset(1, 0), None);
+        assert_eq!(Square::E8.offset(0, 1), None);
+        assert_eq!(Square::E1.offset(0, -1), None);
+        assert_eq!(Square::Invalid.offset(0, 0), None);
+    }
+
+    #[test]
+    fn names() {
+        assert_eq!(Square::from_str("e4"), Ok(Square::E4));
+        assert_eq!(Square::from_str("H8"), Ok(Square::H8));
+        for invalid in ["", "e", "e9", "i4", "e44", "-", " e4", "4e"] {
+            assert!(Square::from_str(invalid).is_err(), "{:?}", invalid);
+        }
+        for square in Square::all() {
+            assert_eq!(Square::from_str(&square.to_string()), Ok(square));
+        }
+        assert_eq!(Square::Invalid.to_string(), "-");
+    }
 }
@@ -0,0 +1,126 @@
+use std::str::FromStr;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{Occupant, Piece, Square};
+
+impl Serialize for Square {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Square::Invalid => serializer.serialize_none(),
+            square => serializer.collect_str(square),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Square {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match Option::<String>::deserialize(deserializer)? {
+            None => Ok(Square::Invalid),
+            Some(name) => Square::from_str(&name)
+                .map_err(|_| D::Error::custom(format!("\"{}\" isn't a square", name))),
+        }
+    }
+}
+
+impl Serialize for Occupant {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.piece().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Occupant {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match Option::<Piece>::deserialize(deserializer)? {
+            Some(piece) => Occupant::Piece(piece),
+            None => Occupant::None,
+        })
+    }
+}
+
+// serde only implements its traits for arrays of up to 32 elements
+pub mod board {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(board: &[Occupant; 64], serializer: S) -> Result<S::Ok, S::Error> {
+        board.as_slice().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[Occupant; 64], D::Error> {
+        let occupants = Vec::<Occupant>::deserialize(deserializer)?;
+        let length = occupants.len();
+        occupants.try_into()
+            .map_err(|_| D::Error::invalid_length(length, &"64 squares"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use crate::bit_board::BitBoardPosition;
+    use crate::definitions::{Move, Occupant, Piece, PieceType, Player, SimplePosition, Square, INITIAL_POSITION};
+
+    #[test]
+    fn squares() {
+        assert_eq!(serde_json::to_string(&Square::E4).unwrap(), "\"e4\"");
+        assert_eq!(serde_json::to_string(&Square::Invalid).unwrap(), "null");
+        assert_eq!(serde_json::from_str::<Square>("\"e4\"").unwrap(), Square::E4);
+        assert_eq!(serde_json::from_str::<Square>("\"H8\"").unwrap(), Square::H8);
+        assert_eq!(serde_json::from_str::<Square>("null").unwrap(), Square::Invalid);
+
+        let err = serde_json::from_str::<Square>("\"i9\"").unwrap_err();
+        assert!(err.to_string().starts_with("\"i9\" isn't a square"), "{}", err);
+        assert!(serde_json::from_str::<Square>("4").is_err());
+    }
+
+    #[test]
+    fn pieces_and_moves() {
+        let knight = Occupant::Piece(Piece { piece_type: PieceType::Knight, owner: Player::White });
+        assert_eq!(serde_json::to_string(&knight).unwrap(), r#"{"type":"knight","owner":"white"}"#);
+        assert_eq!(serde_json::to_string(&Occupant::None).unwrap(), "null");
+        assert_eq!(serde_json::from_str::<Occupant>(r#"{"type":"knight","owner":"white"}"#).unwrap().piece(), knight.piece());
+        assert_eq!(serde_json::from_str::<Occupant>("null").unwrap().piece(), None);
+        assert!(serde_json::from_str::<Occupant>(r#"{"type":"Knight","owner":"white"}"#).is_err());
+
+        let promotion = Move {
+            start: Square::E7,
+            end: Square::E8,
+            piece: Piece { piece_type: PieceType::Pawn, owner: Player::White },
+            promotion: Some(PieceType::Queen),
+        };
+        let text = r#"{"start":"e7","end":"e8","piece":{"type":"pawn","owner":"white"},"promotion":"queen"}"#;
+        assert_eq!(serde_json::to_string(&promotion).unwrap(), text);
+        assert_eq!(serde_json::from_str::<Move>(text).unwrap(), promotion);
+    }
+
+    #[test]
+    fn positions() {
+        let json = serde_json::to_value(&INITIAL_POSITION).unwrap();
+        let board = json["board"].as_array().unwrap();
+        assert_eq!(board.len(), 64);
+        assert_eq!(board[0], json!({ "type": "rook", "owner": "white" }));
+        assert_eq!(board[60], json!({ "type": "king", "owner": "black" }));
+        assert!(board[16..48].iter().all(Value::is_null));
+        assert_eq!(json["to_play"], "white");
+        assert_eq!(json["en_passant_square"], Value::Null);
+        assert_eq!(json["fullmove_number"], 1);
+
+        let text = serde_json::to_string(&INITIAL_POSITION).unwrap();
+        let read: SimplePosition = serde_json::from_str(&text).unwrap();
+        assert_eq!(serde_json::to_string(&read).unwrap(), text);
+
+        // the bitboard position writes the same JSON
+        let position = BitBoardPosition::from_fen("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 3").unwrap();
+        let text = serde_json::to_string(&position).unwrap();
+        assert_eq!(text, serde_json::to_string(&position.to_position()).unwrap());
+        assert!(text.contains(r#""en_passant_square":"e3""#));
+        assert_eq!(serde_json::from_str::<BitBoardPosition>(&text).unwrap().to_fen(), position.to_fen());
+
+        let mut short = json;
+        short["board"].as_array_mut().unwrap().pop();
+        let err = serde_json::from_value::<SimplePosition>(short).unwrap_err();
+        assert!(err.to_string().contains("invalid length 63, expected 64 squares"), "{}", err);
+    }
+}
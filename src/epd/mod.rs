@@ -0,0 +1,313 @@
+use std::fmt;
+
+use crate::bit_board::BitBoardPosition;
+use crate::definitions::{Move, MoveError, PositionError};
+use crate::fen::FenError;
+use crate::san::SanError;
+
+pub mod suite;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EpdError {
+    InvalidFen(FenError),
+    /// The fields read as FEN, but not as a position a game could reach.
+    InvalidPosition(Vec<PositionError>),
+    Syntax(String),
+    InvalidOperand { opcode: String, operand: String },
+    IllegalMove { opcode: String, san: String, reason: SanError },
+    /// A move of a record being written that can't be played.
+    UnplayableMove { opcode: String, reason: MoveError },
+}
+
+impl fmt::Display for EpdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFen(err) => write!(f, "Invalid position: {}", err),
+            Self::InvalidPosition(errors) => {
+                let reasons: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+                write!(f, "Invalid position: {}", reasons.join(", "))
+            },
+            Self::Syntax(reason) => write!(f, "Invalid EPD: {}", reason),
+            Self::InvalidOperand { opcode, operand } => write!(f, "\"{}\" isn't a valid operand for {}", operand, opcode),
+            Self::IllegalMove { opcode, san, reason } => write!(f, "Illegal move {} in {}: {}", san, opcode, reason),
+            Self::UnplayableMove { opcode, reason } => write!(f, "A move in {} can't be played: {}", opcode, reason),
+        }
+    }
+}
+
+impl std::error::Error for EpdError {}
+
+/// A position with the operations describing it. Opcodes without a field
+/// of their own are kept in `operations` as they were read.
+#[derive(Clone, Debug)]
+pub struct EpdRecord {
+    /// Takes its move counters from the `hmvc` and `fmvn` operations.
+    pub position: BitBoardPosition,
+    /// `bm`, the moves a solver should find.
+    pub best_moves: Vec<Move>,
+    /// `am`, the moves a solver should not play.
+    pub avoid_moves: Vec<Move>,
+    pub id: Option<String>,
+    /// `c0`
+    pub comment: Option<String>,
+    /// `acd`, the depth in plies the analysis was searched to.
+    pub depth: Option<u32>,
+    /// `ce`, the evaluation in centipawns for the side to move.
+    pub centipawns: Option<i32>,
+    /// `pv`, played out one after the other from the position.
+    pub principal_variation: Vec<Move>,
+    pub operations: Vec<(String, Vec<String>)>,
+}
+
+// operands of these are written as strings even when they are one word
+const STRING_OPCODES: [&str; 11] = ["id", "c0", "c1", "c2", "c3", "c4", "c5", "c6", "c7", "c8", "c9"];
+
+impl EpdRecord {
+    pub fn new(position: BitBoardPosition) -> Self {
+        EpdRecord {
+            position,
+            best_moves: vec![],
+            avoid_moves: vec![],
+            id: None,
+            comment: None,
+            depth: None,
+            centipawns: None,
+            principal_variation: vec![],
+            operations: vec![],
+        }
+    }
+
+    /// Parses one line of EPD: the first four FEN fields followed by
+    /// operations, each an opcode and its operands ended by `;`. Moves are
+    /// in SAN and checked for legality in the position.
+    pub fn parse(line: &str) -> Result<Self, EpdError> {
+        let mut rest = line.trim();
+        let mut fields = vec![];
+        while fields.len() < 4 && !rest.is_empty() {
+            let end = rest.find(|symbol: char| symbol.is_whitespace() || symbol == ';').unwrap_or(rest.len());
+            fields.push(&rest[..end]);
+            rest = rest[end..].trim_start();
+        }
+
+        let mut operations = parse_operations(rest)?;
+        let mut counter = |opcode: &str, default: &str| -> Result<String, EpdError> {
+            match operations.iter().position(|(name, _)| name == opcode) {
+                Some(index) => single_operand(&operations.remove(index)).map(str::to_string),
+                None => Ok(default.to_string()),
+            }
+        };
+        let fen = format!("{} {} {}", fields.join(" "), counter("hmvc", "0")?, counter("fmvn", "1")?);
+        let position = BitBoardPosition::from_fen(&fen).map_err(EpdError::InvalidFen)?;
+        position.validate().map_err(EpdError::InvalidPosition)?;
+
+        let mut record = EpdRecord::new(position);
+        for operation in operations {
+            let (opcode, operands) = &operation;
+            match opcode.as_str() {
+                "bm" => record.best_moves = record.moves(&operation, false)?,
+                "am" => record.avoid_moves = record.moves(&operation, false)?,
+                "pv" => record.principal_variation = record.moves(&operation, true)?,
+                "id" => record.id = Some(single_operand(&operation)?.to_string()),
+                "c0" => record.comment = Some(single_operand(&operation)?.to_string()),
+                "acd" => record.depth = Some(number_operand(&operation)?),
+                "ce" => record.centipawns = Some(number_operand(&operation)?),
+                _ => record.operations.push((opcode.clone(), operands.clone())),
+            }
+        }
+        Ok(record)
+    }
+
+    // bm and am moves are all from the position, pv moves follow each other
+    fn moves(&self, (opcode, operands): &(String, Vec<String>), in_sequence: bool) -> Result<Vec<Move>, EpdError> {
+        let mut position = self.position.clone();
+        let mut moves = vec![];
+        for san in operands {
+            let illegal = |reason| EpdError::IllegalMove { opcode: opcode.clone(), san: san.clone(), reason };
+            let found = Move::from_san(san, &position).map_err(illegal)?;
+            if in_sequence {
+                position.make_move((found.start, found.end), found.promotion)
+                    .map_err(|reason| illegal(SanError::IllegalMove(san.clone(), reason)))?;
+            }
+            moves.push(found);
+        }
+        Ok(moves)
+    }
+
+    /// The record as a line of EPD, with the operations that have a field
+    /// of their own written first. Fails on the first move that can't be
+    /// played.
+    pub fn to_epd(&self) -> Result<String, EpdError> {
+        let fen = self.position.to_fen();
+        let fields: Vec<&str> = fen.split(' ').collect();
+        let mut epd = fields[..4].join(" ");
+
+        let mut write_operation = |opcode: &str, operands: &[String]| {
+            epd.push(' ');
+            epd.push_str(opcode);
+            for operand in operands {
+                epd.push(' ');
+                let needs_quotes = STRING_OPCODES.contains(&opcode)
+                    || operand.is_empty()
+                    || operand.contains(|symbol: char| symbol.is_whitespace() || symbol == ';' || symbol == '"');
+                if needs_quotes {
+                    epd.push_str(&format!("\"{}\"", operand.replace('"', "'")));
+                } else {
+                    epd.push_str(operand);
+                }
+            }
+            epd.push(';');
+        };
+
+        let sans = |opcode: &str, moves: &[Move], in_sequence: bool| {
+            let mut position = self.position.clone();
+            moves.iter().map(|played_move| {
+                let after = position.play_move((played_move.start, played_move.end), played_move.promotion)
+                    .map_err(|reason| EpdError::UnplayableMove { opcode: opcode.to_string(), reason })?;
+                let san = played_move.to_san(&position);
+                if in_sequence {
+                    position = after;
+                }
+                Ok(san)
+            }).collect::<Result<Vec<String>, EpdError>>()
+        };
+
+        if !self.best_moves.is_empty() {
+            write_operation("bm", &sans("bm", &self.best_moves, false)?);
+        }
+        if !self.avoid_moves.is_empty() {
+            write_operation("am", &sans("am", &self.avoid_moves, false)?);
+        }
+        if let Some(id) = &self.id {
+            write_operation("id", std::slice::from_ref(id));
+        }
+        if let Some(comment) = &self.comment {
+            write_operation("c0", std::slice::from_ref(comment));
+        }
+        if let Some(depth) = self.depth {
+            write_operation("acd", &[depth.to_string()]);
+        }
+        if let Some(centipawns) = self.centipawns {
+            write_operation("ce", &[centipawns.to_string()]);
+        }
+        if !self.principal_variation.is_empty() {
+            write_operation("pv", &sans("pv", &self.principal_variation, true)?);
+        }
+        for (opcode, operands) in &self.operations {
+            write_operation(opcode, operands);
+        }
+        if fields[4] != "0" {
+            write_operation("hmvc", &[fields[4].to_string()]);
+        }
+        if fields[5] != "1" {
+            write_operation("fmvn", &[fields[5].to_string()]);
+        }
+        Ok(epd)
+    }
+}
+
+fn single_operand((opcode, operands): &(String, Vec<String>)) -> Result<&str, EpdError> {
+    match operands.as_slice() {
+        [operand] => Ok(operand),
+        _ => Err(EpdError::InvalidOperand { opcode: opcode.clone(), operand: operands.join(" ") }),
+    }
+}
+
+fn number_operand<T: std::str::FromStr>(operation: &(String, Vec<String>)) -> Result<T, EpdError> {
+    let operand = single_operand(operation)?;
+    operand.parse().map_err(|_| EpdError::InvalidOperand { opcode: operation.0.clone(), operand: operand.to_string() })
+}
+
+// empty operations, such as the `;` some suites put straight after the
+// position, are skipped and the last one may leave off its `;`
+fn parse_operations(text: &str) -> Result<Vec<(String, Vec<String>)>, EpdError> {
+    let mut operations = vec![];
+    let mut tokens: Vec<String> = vec![];
+    let mut chars = text.chars().peekable();
+
+    let mut finish_operation = |tokens: &mut Vec<String>| {
+        if !tokens.is_empty() {
+            let opcode = tokens.remove(0);
+            operations.push((opcode, std::mem::take(tokens)));
+        }
+    };
+
+    while let Some(symbol) = chars.next() {
+        match symbol {
+            _ if symbol.is_whitespace() => (),
+            ';' => finish_operation(&mut tokens),
+            '"' => {
+                if tokens.is_empty() {
+                    return Err(EpdError::Syntax("an operation has to start with an opcode, not a string".to_string()));
+                }
+                let mut string = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(next) => string.push(next),
+                        None => return Err(EpdError::Syntax("string is never closed".to_string())),
+                    }
+                }
+                tokens.push(string);
+            },
+            _ => {
+                let mut token = symbol.to_string();
+                while let Some(&next) = chars.peek() {
+                    if next.is_whitespace() || next == ';' || next == '"' { break; }
+                    token.push(next);
+                    chars.next();
+                }
+                tokens.push(token);
+            },
+        }
+    }
+    finish_operation(&mut tokens);
+
+    Ok(operations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EpdError, EpdRecord};
+    use crate::definitions::{MoveError, PieceType, Player, PositionError, Square};
+
+    #[test]
+    fn round_trip() {
+        let line = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\"; c0 \"mate; soon\"; acd 12; ce 32000; pv Qg6 fxg6 Nxg6#; c1 \"x\"; hmvc 3; fmvn 20;";
+        let record = EpdRecord::parse(line).unwrap();
+        assert_eq!(record.id.as_deref(), Some("WAC.001"));
+        assert_eq!(record.comment.as_deref(), Some("mate; soon"));
+        assert_eq!((record.depth, record.centipawns), (Some(12), Some(32000)));
+        assert_eq!(record.principal_variation.len(), 3);
+        assert_eq!(record.operations, vec![("c1".to_string(), vec!["x".to_string()])]);
+        assert_eq!(record.position.to_fen(), "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - 3 20");
+
+        assert_eq!(record.to_epd().unwrap(), line);
+        let again = EpdRecord::parse(&record.to_epd().unwrap()).unwrap();
+        assert_eq!(again.best_moves, record.best_moves);
+        assert_eq!(again.principal_variation, record.principal_variation);
+    }
+
+    #[test]
+    fn invalid_positions() {
+        assert_eq!(
+            EpdRecord::parse("7r/8/8/8/8/8/8/K6K w - - pv Kb1 Rh7;").err(),
+            Some(EpdError::InvalidPosition(vec![PositionError::TooManyKings(Player::White), PositionError::MissingKing(Player::Black)])),
+        );
+        assert_eq!(
+            EpdRecord::parse("4k3/8/8/8/8/8/8/4K2r b - - bm Kd7;").err(),
+            Some(EpdError::InvalidPosition(vec![PositionError::OpponentInCheck])),
+        );
+        assert!(matches!(EpdRecord::parse("4k3/8/8 w - - bm e4;"), Err(EpdError::InvalidFen(_))));
+    }
+
+    #[test]
+    fn unplayable_moves() {
+        let mut record = EpdRecord::parse("4k3/8/8/8/8/8/4P3/4K3 w - - pv e4 Kd7;").unwrap();
+        record.principal_variation.swap(0, 1);
+        assert_eq!(record.to_epd(), Err(EpdError::UnplayableMove { opcode: "pv".to_string(), reason: MoveError::NotYourPiece }));
+
+        let mut record = EpdRecord::parse("4k3/8/8/8/8/8/4P3/4K3 w - - bm e4;").unwrap();
+        record.best_moves[0].end = Square::E5;
+        assert_eq!(record.to_epd(), Err(EpdError::UnplayableMove { opcode: "bm".to_string(), reason: MoveError::IllegalPieceMove(PieceType::Pawn) }));
+    }
+}
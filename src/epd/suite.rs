@@ -0,0 +1,212 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::bit_board::BitBoardPosition;
+use crate::definitions::Move;
+
+use super::{EpdError, EpdRecord};
+
+/// The records of an EPD file, a position per line. Blank lines and lines
+/// starting with `#` are skipped.
+pub struct EpdSuite {
+    /// Line numbers, starting at 1, with what was read from them.
+    pub entries: Vec<(usize, Result<EpdRecord, EpdError>)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SuiteFailure {
+    /// The line didn't parse, or its position isn't valid.
+    Unreadable(EpdError),
+    /// A `D<depth>` operation, as in perft suites, with another node count.
+    PerftMismatch { depth: u32, expected: u64, found: u64 },
+    NoMoveFound,
+    MissedBestMove(Move),
+    PlayedAvoidMove(Move),
+}
+
+impl fmt::Display for SuiteFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable(err) => write!(f, "{}", err),
+            Self::PerftMismatch { depth, expected, found } => write!(f, "Perft({}) expected {} nodes, found {}", depth, expected, found),
+            Self::NoMoveFound => write!(f, "The search didn't return a move"),
+            Self::MissedBestMove(found) => write!(f, "Played {} instead of a best move", found.to_uci()),
+            Self::PlayedAvoidMove(found) => write!(f, "Played {}, a move to avoid", found.to_uci()),
+        }
+    }
+}
+
+impl std::error::Error for SuiteFailure {}
+
+pub struct SuiteResult {
+    pub line: usize,
+    pub id: Option<String>,
+    pub failures: Vec<SuiteFailure>,
+}
+
+impl SuiteResult {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+type Search<'a> = &'a mut dyn FnMut(&BitBoardPosition) -> Option<Move>;
+
+impl EpdSuite {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(EpdSuite::parse(&fs::read_to_string(path)?))
+    }
+
+    pub fn parse(text: &str) -> Self {
+        let entries = text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+            .map(|(index, line)| (index + 1, EpdRecord::parse(line)))
+            .collect();
+        EpdSuite { entries }
+    }
+
+    /// Checks every position is valid and, for the records with `D<depth>`
+    /// operations, that the move generator finds that many nodes.
+    pub fn check(&self) -> Vec<SuiteResult> {
+        self.run(None)
+    }
+
+    /// Runs `check` and also has `search` pick a move in the positions with
+    /// `bm` or `am` operations. Picking one of the best moves and none of
+    /// the ones to avoid solves a position.
+    pub fn solve<F: FnMut(&BitBoardPosition) -> Option<Move>>(&self, mut search: F) -> Vec<SuiteResult> {
+        self.run(Some(&mut search))
+    }
+
+    fn run(&self, mut search: Option<Search>) -> Vec<SuiteResult> {
+        self.entries.iter().map(|(line, entry)| {
+            let record = match entry {
+                Ok(record) => record,
+                Err(err) => return SuiteResult { line: *line, id: None, failures: vec![SuiteFailure::Unreadable(err.clone())] },
+            };
+
+            let mut failures = perft_failures(record);
+            if let Some(search) = search.as_mut() {
+                failures.extend(search_failure(record, search));
+            }
+
+            SuiteResult { line: *line, id: record.id.clone(), failures }
+        }).collect()
+    }
+}
+
+fn perft_failures(record: &EpdRecord) -> Vec<SuiteFailure> {
+    let mut failures = vec![];
+    for (opcode, operands) in &record.operations {
+        let depth = match opcode.strip_prefix('D').and_then(|depth| depth.parse::<u32>().ok()) {
+            Some(depth) => depth,
+            None => continue,
+        };
+        let expected = match operands.as_slice() {
+            [operand] => match operand.parse::<u64>() {
+                Ok(expected) => expected,
+                Err(_) => {
+                    let err = EpdError::InvalidOperand { opcode: opcode.clone(), operand: operand.clone() };
+                    failures.push(SuiteFailure::Unreadable(err));
+                    continue;
+                },
+            },
+            _ => {
+                let err = EpdError::InvalidOperand { opcode: opcode.clone(), operand: operands.join(" ") };
+                failures.push(SuiteFailure::Unreadable(err));
+                continue;
+            },
+        };
+
+        let found = record.position.perft(depth);
+        if found != expected {
+            failures.push(SuiteFailure::PerftMismatch { depth, expected, found });
+        }
+    }
+    failures
+}
+
+fn search_failure(record: &EpdRecord, search: &mut Search) -> Option<SuiteFailure> {
+    if record.best_moves.is_empty() && record.avoid_moves.is_empty() {
+        return None;
+    }
+    let found = match search(&record.position) {
+        Some(found) => found,
+        None => return Some(SuiteFailure::NoMoveFound),
+    };
+
+    if record.avoid_moves.contains(&found) {
+        return Some(SuiteFailure::PlayedAvoidMove(found));
+    }
+    if !record.best_moves.is_empty() && !record.best_moves.contains(&found) {
+        return Some(SuiteFailure::MissedBestMove(found));
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EpdSuite, SuiteFailure};
+    use crate::definitions::{Player, PositionError, Square};
+    use crate::epd::EpdError;
+
+    #[test]
+    fn perft_checks() {
+        let suite = EpdSuite::parse(concat!(
+            "# perft\n",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - D1 20; D2 400; id \"start\";\n",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - D1 20; D2 401; id \"off by one\";\n",
+            "\n",
+            "4k3/8/8 w - - D1 5;\n",
+            "7k/8/8/8/8/8/8/K6K w - - D1 3;\n",
+            "4k3/8/8/8/8/8/8/4K3 w - - D1 5; D2 x;\n",
+        ));
+        let results = suite.check();
+
+        let lines: Vec<usize> = results.iter().map(|result| result.line).collect();
+        assert_eq!(lines, vec![2, 3, 5, 6, 7]);
+
+        assert!(results[0].passed());
+        assert_eq!(results[0].id.as_deref(), Some("start"));
+
+        assert_eq!(results[1].id.as_deref(), Some("off by one"));
+        assert_eq!(results[1].failures, vec![SuiteFailure::PerftMismatch { depth: 2, expected: 401, found: 400 }]);
+
+        assert!(matches!(results[2].failures.as_slice(), [SuiteFailure::Unreadable(EpdError::InvalidFen(_))]));
+        let two_kings = EpdError::InvalidPosition(vec![PositionError::TooManyKings(Player::White)]);
+        assert_eq!(results[3].failures, vec![SuiteFailure::Unreadable(two_kings)]);
+        assert_eq!(results[4].failures, vec![SuiteFailure::Unreadable(EpdError::InvalidOperand { opcode: "D2".to_string(), operand: "x".to_string() })]);
+    }
+
+    #[test]
+    fn solving() {
+        let suite = EpdSuite::parse(concat!(
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Ra8#; id \"mate\";\n",
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - am Ra8; id \"avoid\";\n",
+            "6k1/5ppp/8/8/8/8/8/R5K1 w - - bm Re1; id \"missed\";\n",
+            "6k1/5ppp/8/8/8/8/8/6K1 w - - bm Kf1; id \"no move\";\n",
+            "6k1/5ppp/8/8/8/8/8/6K1 w - - id \"nothing to solve\";\n",
+        ));
+        // plays to a8 whenever it can
+        let results = suite.solve(|position| position.legal_moves().into_iter().find(|legal_move| legal_move.end == Square::A8));
+        let failures = results.iter().map(|result| result.failures.clone()).collect::<Vec<_>>();
+
+        let rook_to_a8 = match &failures[1][..] {
+            [SuiteFailure::PlayedAvoidMove(played)] => *played,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!((rook_to_a8.start, rook_to_a8.end), (Square::A1, Square::A8));
+        assert_eq!(failures, vec![
+            vec![],
+            vec![SuiteFailure::PlayedAvoidMove(rook_to_a8)],
+            vec![SuiteFailure::MissedBestMove(rook_to_a8)],
+            vec![SuiteFailure::NoMoveFound],
+            vec![],
+        ]);
+
+        assert!(suite.check().iter().all(|result| result.passed()));
+    }
+}
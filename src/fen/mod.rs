@@ -0,0 +1,266 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bit_board::BitBoardPosition;
+use crate::definitions::{Occupant, Piece, PieceType, Player, Rank, SimplePosition, Square};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FenError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    /// The rank number, 8 for the first one in the string.
+    WrongRankLength(usize),
+    InvalidPiece(char),
+    InvalidSideToMove(String),
+    InvalidCastlingRights(String),
+    InvalidEnPassantSquare(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongFieldCount(count) => write!(f, "FEN needs 4 or 6 space separated fields, found {}", count),
+            Self::WrongRankCount(count) => write!(f, "Piece placement needs 8 ranks separated by '/', found {}", count),
+            Self::WrongRankLength(rank) => write!(f, "Rank {} doesn't describe exactly 8 squares", rank),
+            Self::InvalidPiece(symbol) => write!(f, "'{}' isn't a piece", symbol),
+            Self::InvalidSideToMove(field) => write!(f, "Side to move has to be 'w' or 'b', found \"{}\"", field),
+            Self::InvalidCastlingRights(field) => write!(f, "Castling rights have to be '-' or made of 'KQkq', found \"{}\"", field),
+            Self::InvalidEnPassantSquare(field) => write!(f, "En passant square has to be '-' or a square on the third or sixth rank, found \"{}\"", field),
+            Self::InvalidHalfmoveClock(field) => write!(f, "Halfmove clock has to be a number, found \"{}\"", field),
+            Self::InvalidFullmoveNumber(field) => write!(f, "Fullmove number has to be a positive number, found \"{}\"", field),
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+impl Piece {
+    /// Uppercase letter for White, lowercase for Black.
+    pub fn fen_char(&self) -> char {
+        let symbol = match self.piece_type {
+            PieceType::Pawn => 'p',
+            PieceType::Rook => 'r',
+            PieceType::Knight => 'n',
+            PieceType::Bishop => 'b',
+            PieceType::Queen => 'q',
+            PieceType::King => 'k',
+        };
+        match self.owner {
+            Player::White => symbol.to_ascii_uppercase(),
+            Player::Black => symbol,
+        }
+    }
+
+    pub fn from_fen_char(symbol: char) -> Option<Self> {
+        let piece_type = match symbol.to_ascii_lowercase() {
+            'p' => PieceType::Pawn,
+            'r' => PieceType::Rook,
+            'n' => PieceType::Knight,
+            'b' => PieceType::Bishop,
+            'q' => PieceType::Queen,
+            'k' => PieceType::King,
+            _ => return None,
+        };
+        let owner = if symbol.is_ascii_uppercase() { Player::White } else { Player::Black };
+        Some(Piece { piece_type, owner })
+    }
+}
+
+impl SimplePosition {
+    /// Parses all six FEN fields. The move counters may be left off, as
+    /// EPD does, and then default to `0 1`. Only the syntax is checked, see
+    /// `BitBoardPosition::validate` for whether the position makes sense.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if fields.len() != 4 && fields.len() != 6 {
+            return Err(FenError::WrongFieldCount(fields.len()));
+        }
+
+        let board = parse_placement(fields[0])?;
+
+        let to_play = match fields[1] {
+            "w" => Player::White,
+            "b" => Player::Black,
+            other => return Err(FenError::InvalidSideToMove(other.to_string())),
+        };
+
+        let castling = fields[2];
+        let castling_is_valid = castling == "-"
+            || (!castling.is_empty()
+                && castling.chars().all(|symbol| "KQkq".contains(symbol))
+                && castling.chars().enumerate().all(|(index, symbol)| !castling[index + 1..].contains(symbol)));
+        if !castling_is_valid {
+            return Err(FenError::InvalidCastlingRights(castling.to_string()));
+        }
+
+        let en_passant_square = match fields[3] {
+            "-" => Square::Invalid,
+            field => match Square::from_str(field) {
+                Ok(square) if matches!(square.rank(), Rank::Third | Rank::Sixth) => square,
+                _ => return Err(FenError::InvalidEnPassantSquare(field.to_string())),
+            },
+        };
+
+        let (halfmove_clock, fullmove_number) = match fields.get(4..6) {
+            Some([halfmove_clock, fullmove_number]) => (
+                halfmove_clock.parse().map_err(|_| FenError::InvalidHalfmoveClock(halfmove_clock.to_string()))?,
+                fullmove_number.parse().ok().filter(|number| *number > 0)
+                    .ok_or_else(|| FenError::InvalidFullmoveNumber(fullmove_number.to_string()))?,
+            ),
+            _ => (0, 1),
+        };
+
+        Ok(SimplePosition {
+            board,
+            to_play,
+            en_passant_square,
+            white_king_side_castle: castling.contains('K'),
+            white_queen_side_castle: castling.contains('Q'),
+            black_king_side_castle: castling.contains('k'),
+            black_queen_side_castle: castling.contains('q'),
+            halfmove_clock,
+            fullmove_number,
+        })
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut placement = String::new();
+        for rank in (0..8).rev() {
+            let mut empty_squares = 0;
+            for file in 0..8 {
+                match self.board[rank * 8 + file] {
+                    Occupant::Piece(piece) => {
+                        if empty_squares > 0 {
+                            placement.push_str(&empty_squares.to_string());
+                            empty_squares = 0;
+                        }
+                        placement.push(piece.fen_char());
+                    },
+                    Occupant::None => empty_squares += 1,
+                }
+            }
+            if empty_squares > 0 {
+                placement.push_str(&empty_squares.to_string());
+            }
+            if rank > 0 {
+                placement.push('/');
+            }
+        }
+
+        let mut castling = String::new();
+        for (has_right, symbol) in [
+            (self.white_king_side_castle, 'K'),
+            (self.white_queen_side_castle, 'Q'),
+            (self.black_king_side_castle, 'k'),
+            (self.black_queen_side_castle, 'q'),
+        ] {
+            if has_right { castling.push(symbol); }
+        }
+        if castling.is_empty() {
+            castling.push('-');
+        }
+
+        format!(
+            "{} {} {} {} {} {}",
+            placement,
+            if self.to_play == Player::White { "w" } else { "b" },
+            castling,
+            self.en_passant_square,
+            self.halfmove_clock,
+            self.fullmove_number,
+        )
+    }
+}
+
+impl BitBoardPosition {
+    /// See `SimplePosition::from_fen`.
+    pub fn from_fen(fen: &str) -> Result<Self, FenError> {
+        Ok(BitBoardPosition::from_position(&SimplePosition::from_fen(fen)?))
+    }
+
+    pub fn to_fen(&self) -> String {
+        self.to_position().to_fen()
+    }
+}
+
+fn parse_placement(placement: &str) -> Result<[Occupant; 64], FenError> {
+    let ranks: Vec<&str> = placement.split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+
+    let mut board = [Occupant::None; 64];
+    for (row, rank) in ranks.iter().enumerate() {
+        let rank_number = 8 - row;
+        let mut file = 0;
+        for symbol in rank.chars() {
+            if let Some(empty_squares @ 1..=8) = symbol.to_digit(10) {
+                file += empty_squares as usize;
+                continue;
+            }
+            let piece = Piece::from_fen_char(symbol).ok_or(FenError::InvalidPiece(symbol))?;
+            if file >= 8 {
+                return Err(FenError::WrongRankLength(rank_number));
+            }
+            board[(rank_number - 1) * 8 + file] = Occupant::Piece(piece);
+            file += 1;
+        }
+        if file != 8 {
+            return Err(FenError::WrongRankLength(rank_number));
+        }
+    }
+    Ok(board)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FenError;
+    use crate::bit_board::BitBoardPosition;
+    use crate::definitions::{SimplePosition, INITIAL_POSITION};
+
+    const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+    fn error(fen: &str) -> FenError {
+        SimplePosition::from_fen(fen).unwrap_err()
+    }
+
+    #[test]
+    fn round_trip() {
+        assert_eq!(INITIAL_POSITION.to_fen(), STARTING_FEN);
+        for fen in [
+            STARTING_FEN,
+            "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R b Kq - 13 47",
+            "8/8/8/8/8/8/8/k6K w - - 99 120",
+        ] {
+            assert_eq!(SimplePosition::from_fen(fen).unwrap().to_fen(), fen);
+            assert_eq!(BitBoardPosition::from_fen(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    #[test]
+    fn move_counters_are_optional() {
+        let position = SimplePosition::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -").unwrap();
+        assert_eq!(position.to_fen(), STARTING_FEN);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0"), FenError::WrongFieldCount(5));
+        assert_eq!(error(""), FenError::WrongFieldCount(0));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), FenError::WrongRankCount(7));
+        assert_eq!(error("rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), FenError::WrongRankLength(7));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNRR w KQkq - 0 1"), FenError::WrongRankLength(1));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/4X3/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"), FenError::InvalidPiece('X'));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1"), FenError::InvalidSideToMove("x".to_string()));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KK - 0 1"), FenError::InvalidCastlingRights("KK".to_string()));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQx - 0 1"), FenError::InvalidCastlingRights("KQx".to_string()));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq e4 0 1"), FenError::InvalidEnPassantSquare("e4".to_string()));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq z9 0 1"), FenError::InvalidEnPassantSquare("z9".to_string()));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - -1 1"), FenError::InvalidHalfmoveClock("-1".to_string()));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0"), FenError::InvalidFullmoveNumber("0".to_string()));
+        assert_eq!(error("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 x"), FenError::InvalidFullmoveNumber("x".to_string()));
+    }
+}
@@ -0,0 +1,9 @@
+pub mod binary;
+pub mod bit_board;
+pub mod definitions;
+pub mod epd;
+pub mod fen;
+pub mod pgn;
+pub mod render;
+pub mod san;
+pub mod uci;
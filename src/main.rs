@@ -1,19 +1,20 @@
-mod bit_board;
-mod definitions;
-mod wasm;
-
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufReader;
 use std::str::FromStr;
 
-use bit_board::{BitBoardPosition, calculations::precalculations::PreComputedAttackSets};
-use bit_board::calculations::precalculations;
-use definitions::{
+use five_d_chess::bit_board::{BitBoardPosition, UndoRecord};
+use five_d_chess::epd::suite::EpdSuite;
+use five_d_chess::pgn::{GameResult, PgnGame, PgnMove, PgnReader};
+use five_d_chess::render::{RenderOptions, RenderStyle};
+use five_d_chess::definitions::{
     INITIAL_POSITION,
-    SimplePosition,
     Square,
     Player,
-    Piece,
     PieceType,
-    Occupant,
+    GameStatus,
+    Move,
+    MoveError,
 };
 use std::io::{stdin, stdout, Write};
 
@@ -21,59 +22,199 @@ fn main() {
     use std::env;
     env::set_var("RUST_BACKTRACE", "1");
 
-    start_game();
-}
+    // `epd <file>` checks the positions of a test suite instead of playing
+    if env::args().nth(1).as_deref() == Some("epd") {
+        match env::args().nth(2) {
+            Some(path) => check_suite(&path),
+            None => println!("Give the EPD file to check"),
+        }
+        return;
+    }
 
-fn start_game() {
-    let attack_sets = precalculations::build_piece_attack_set();
-    let mut position = BitBoardPosition::from_position(&INITIAL_POSITION);
-    
-    let mut position_history: Vec<BitBoardPosition> = vec![];
-
-    loop {
-        if position.promotion_square == Square::Invalid {
-            position = handle_move(position, &attack_sets);
-            if position.promotion_square == Square::Invalid {
-                position_history.push(position.clone());
-            }
-        } else {
-            position = handle_promotion(position);
-            position_history.push(position.clone());
+    // an optional FEN argument starts the game from that position
+    let position = match env::args().nth(1) {
+        None => BitBoardPosition::from_position(&INITIAL_POSITION),
+        Some(fen) => match BitBoardPosition::from_fen(&fen) {
+            Ok(position) => position,
+            Err(err) => {
+                println!("{}", err);
+                return;
+            },
+        },
+    };
+
+    if let Err(errors) = position.validate() {
+        for err in errors {
+            println!("{}", err);
         }
+        return;
     }
+
+    start_game(position);
 }
 
-fn handle_promotion(position: BitBoardPosition) -> BitBoardPosition {
-    println!("\n\n{}", position.to_position().to_string());
-    let mut input = String::new();
-    let _=stdout().flush();
-    stdin().read_line(&mut input).expect("Error on move entry");
-    if let Some('\n')=input.chars().next_back() {
-        input.pop();
-    }
-    if let Some('\r')=input.chars().next_back() {
-        input.pop();
+fn check_suite(path: &str) {
+    let suite = match EpdSuite::load(path) {
+        Ok(suite) => suite,
+        Err(err) => {
+            println!("Couldn't read {}: {}", path, err);
+            return;
+        },
+    };
+
+    let results = suite.check();
+    for result in results.iter().filter(|result| !result.passed()) {
+        let id = result.id.as_ref().map(|id| format!(" ({})", id)).unwrap_or_default();
+        for failure in &result.failures {
+            println!("Line {}{}: {}", result.line, id, failure);
+        }
     }
+    let passed = results.iter().filter(|result| result.passed()).count();
+    println!("{} of {} positions passed", passed, results.len());
+}
+
+fn start_game(mut position: BitBoardPosition) {
+    let mut start_position = position.clone();
+    let mut position_history: Vec<u64> = vec![position.zobrist_key()];
+    let mut undo_history: Vec<UndoRecord> = vec![];
+    let mut render_options = RenderOptions { side_panel: true, ..RenderOptions::default() };
+
+    let result = loop {
+        render_options.last_move = undo_history.last().map(|undo| (undo.played_move.start, undo.played_move.end));
+        println!("\n\n{}\n", position.render(&render_options));
+
+        let claimable_draw = position.claimable_draw(&position_history);
+        if let Some(reason) = claimable_draw {
+            println!("{:?} reached, enter \"claim\" to end the game as a draw", reason);
+        }
+
+        let input = read_input();
+
+        if input == "claim" && claimable_draw.is_some() {
+            println!("Draw claimed");
+            break GameResult::Draw;
+        }
+
+        if let Some(path) = input.strip_prefix("save ") {
+            save_game(path, &start_position, &undo_history, GameResult::Unknown);
+            continue;
+        }
+
+        if let Some(path) = input.strip_prefix("load ") {
+            match load_game(path) {
+                Ok(game) => {
+                    // the moves are played again so they can be undone
+                    start_position = game.start_position.clone();
+                    position = game.start_position;
+                    position_history = vec![position.zobrist_key()];
+                    undo_history = vec![];
+                    for pgn_move in game.moves {
+                        let played_move = pgn_move.played_move;
+                        undo_history.push(position.make_move((played_move.start, played_move.end), played_move.promotion)
+                            .expect("moves were replayed when the game was read"));
+                        position_history.push(position.zobrist_key());
+                    }
+                },
+                Err(err) => println!("Couldn't load {}: {}", path, err),
+            }
+            continue;
+        }
+
+        // board display commands
+        if input == "flip" {
+            render_options.orientation = render_options.orientation.opponent();
+            continue;
+        }
+        if input == "panel" {
+            render_options.side_panel = !render_options.side_panel;
+            continue;
+        }
+        if let Some(style) = input.strip_prefix("style ") {
+            match style {
+                "ascii" => render_options.style = RenderStyle::Ascii,
+                "unicode" => render_options.style = RenderStyle::Unicode,
+                "ansi" => render_options.style = RenderStyle::Ansi,
+                _ => println!("Styles are ascii, unicode and ansi"),
+            }
+            continue;
+        }
 
-    let piece_type = match input.as_str() {
-        "rook" => Some(PieceType::Rook),
-        "queen" => Some(PieceType::Queen),
-        "knight" => Some(PieceType::Knight),
-        "bishop" => Some(PieceType::Bishop),
-        _ => None
+        if input == "undo" {
+            if let Some(undo) = undo_history.pop() {
+                position.unmake_move(undo);
+                position_history.pop();
+            }
+            continue;
+        }
+
+        match handle_move(&mut position, &input) {
+            Ok(undo) => undo_history.push(undo),
+            Err(err) => {
+                println!("{}", err);
+                continue;
+            },
+        }
+
+        position_history.push(position.zobrist_key());
+        render_options.last_move = undo_history.last().map(|undo| (undo.played_move.start, undo.played_move.end));
+
+        match position.status(&position_history) {
+            GameStatus::Ongoing => (),
+            GameStatus::Checkmate(winner) => {
+                println!("\n\n{}\n", position.render(&render_options));
+                println!("Checkmate, {:?} wins", winner);
+                break match winner {
+                    Player::White => GameResult::WhiteWins,
+                    Player::Black => GameResult::BlackWins,
+                };
+            },
+            GameStatus::Stalemate => {
+                println!("\n\n{}\n", position.render(&render_options));
+                println!("Stalemate, the game is a draw");
+                break GameResult::Draw;
+            },
+            GameStatus::Draw(reason) => {
+                println!("\n\n{}\n", position.render(&render_options));
+                println!("{:?}, the game is a draw", reason);
+                break GameResult::Draw;
+            },
+        }
     };
 
-    if piece_type.is_none() {
-        return position
+    println!("Enter a file name to save the game as PGN, or nothing to quit");
+    let path = read_input();
+    if !path.is_empty() {
+        save_game(&path, &start_position, &undo_history, result);
     }
-
-     position.promote(piece_type.unwrap()).unwrap()
 }
 
-fn handle_move(position: BitBoardPosition, attack_sets: &PreComputedAttackSets) -> BitBoardPosition {
-    println!("\n\n{}", position.to_position().to_string());
+fn save_game(path: &str, start_position: &BitBoardPosition, undo_history: &[UndoRecord], result: GameResult) {
+    let mut game = PgnGame::new(start_position.clone());
+    game.result = result;
+    game.moves = undo_history
+        .iter()
+        .map(|undo| PgnMove { played_move: undo.played_move, comment: None, nags: vec![], clock: None, variations: vec![] })
+        .collect();
 
+    let saved = game.to_pgn()
+        .map_err(|err| err.to_string())
+        .and_then(|pgn| fs::write(path, pgn).map_err(|err| err.to_string()));
+    match saved {
+        Ok(()) => println!("Game saved to {}", path),
+        Err(err) => println!("Couldn't save the game: {}", err),
+    }
+}
+
+// the first game in the file
+fn load_game(path: &str) -> Result<PgnGame, Box<dyn Error>> {
+    let file = File::open(path)?;
+    match PgnReader::new(BufReader::new(file)).next() {
+        Some(game) => Ok(game?),
+        None => Err("there are no games in it".into()),
+    }
+}
 
+fn read_input() -> String {
     let mut input = String::new();
     let _=stdout().flush();
     stdin().read_line(&mut input).expect("Error on move entry");
@@ -83,38 +224,58 @@ fn handle_move(position: BitBoardPosition, attack_sets: &PreComputedAttackSets)
     if let Some('\r')=input.chars().next_back() {
         input.pop();
     }
+    input
+}
 
-    let mut move_instruction: (Square, Square) = (Square::Invalid, Square::Invalid);
+// moves are either in algebraic notation, "Nf3", coordinate notation,
+// "g1f3", or a pair of squares with an optional promotion, "E7,E8,queen"
+fn handle_move(position: &mut BitBoardPosition, input: &str) -> Result<UndoRecord, Box<dyn Error>> {
+    if !input.contains(',') {
+        let typed_move = Move::from_san(input, position)
+            .or_else(|err| Move::from_uci(input, position).map_err(|_| err))?;
+        return Ok(position.make_move((typed_move.start, typed_move.end), typed_move.promotion)?);
+    }
 
-    let mut square_iter = input.split(","); 
+    let mut input_iter = input.split(","); 
 
-    if let Some(square_string) = square_iter.next() {
-        if let Ok(square) = Square::from_str(square_string) {
-            move_instruction.0 = square;
-        }
-    } else {
-        println!("invalid input");
-    }
+    let start = input_iter.next().and_then(|square_string| Square::from_str(square_string).ok());
+    let end = input_iter.next().and_then(|square_string| Square::from_str(square_string).ok());
 
-    if let Some(square_string) = square_iter.next() {
-        if let Ok(square) = Square::from_str(square_string) {
-            move_instruction.1 = square;
-        }
-    } else {
-        println!("invalid input");
+    // a missing piece is left to make_move, which knows whether one is needed
+    let promotion = match input_iter.next().map(str::trim) {
+        None => None,
+        Some("rook") => Some(PieceType::Rook),
+        Some("queen") => Some(PieceType::Queen),
+        Some("knight") => Some(PieceType::Knight),
+        Some("bishop") => Some(PieceType::Bishop),
+        Some(word) => return Err(format!("\"{}\" isn't a piece to promote to, use queen, rook, bishop or knight", word).into()),
+    };
+
+    match (start, end) {
+        (Some(start), Some(end)) => Ok(position.make_move((start, end), promotion)?),
+        _ => Err(MoveError::InvalidSquare.into()),
     }
+}
 
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    if move_instruction.0 == Square::Invalid || move_instruction.1 == Square::Invalid {
-        println!("Invalid square");
-        return position;
+    fn move_error(fen: &str, input: &str) -> Option<MoveError> {
+        let mut position = BitBoardPosition::from_fen(fen).unwrap();
+        handle_move(&mut position, input).unwrap_err().downcast_ref::<MoveError>().copied()
     }
 
-    match position.play_move(move_instruction, &attack_sets) {
-        Ok(_position) => _position,
-        Err(_position) => { 
-            println!("Illegal Move");
-            _position
-        }
+    #[test]
+    fn promotion_words() {
+        let promotion = "4k3/P7/8/8/8/8/8/4K3 w - - 0 1";
+        assert_eq!(move_error(promotion, "A7,A8"), Some(MoveError::PromotionRequired));
+        assert_eq!(move_error(promotion, "A7,A8,foo"), None);
+        assert_eq!(move_error(promotion, "E1,E2,queen"), Some(MoveError::UnexpectedPromotion));
+
+        let mut position = BitBoardPosition::from_fen(promotion).unwrap();
+        let err = handle_move(&mut position, "A7,A8,foo").unwrap_err();
+        assert!(err.to_string().contains("\"foo\" isn't a piece to promote to"));
+        assert!(handle_move(&mut position, "A7,A8,knight").is_ok());
     }
 }
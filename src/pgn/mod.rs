@@ -0,0 +1,610 @@
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+use std::time::Duration;
+
+use crate::bit_board::BitBoardPosition;
+use crate::definitions::{Move, MoveError, PositionError, INITIAL_POSITION};
+use crate::fen::FenError;
+use crate::san::SanError;
+
+mod writer;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    /// Unfinished, abandoned or unknown, written `*`.
+    Unknown,
+}
+
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WhiteWins => write!(f, "1-0"),
+            Self::BlackWins => write!(f, "0-1"),
+            Self::Draw => write!(f, "1/2-1/2"),
+            Self::Unknown => write!(f, "*"),
+        }
+    }
+}
+
+impl FromStr for GameResult {
+    type Err = &'static str;
+    fn from_str(s: &str) -> Result<Self, &'static str> {
+        match s {
+            "1-0" => Ok(Self::WhiteWins),
+            "0-1" => Ok(Self::BlackWins),
+            "1/2-1/2" => Ok(Self::Draw),
+            "*" => Ok(Self::Unknown),
+            _ => Err("Invalid game result"),
+        }
+    }
+}
+
+/// A mainline move with the annotations that followed it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PgnMove {
+    pub played_move: Move,
+    pub comment: Option<String>,
+    pub nags: Vec<u8>,
+    /// Time left on the mover's clock, read from and written as a
+    /// `[%clk 1:05:30]` command in the comment.
+    pub clock: Option<Duration>,
+    /// Movetext of the variations given as alternatives to this move, as
+    /// written between the parentheses. They aren't replayed.
+    pub variations: Vec<String>,
+}
+
+#[derive(Clone, Debug)]
+pub struct PgnGame {
+    /// Tag pairs in the order they were read.
+    pub tags: Vec<(String, String)>,
+    /// The initial position, or the one in the FEN tag.
+    pub start_position: BitBoardPosition,
+    /// Comment before the first move.
+    pub comment: Option<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: GameResult,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag_name, _)| tag_name == name).map(|(_, value)| value.as_str())
+    }
+
+    /// The position after the last mainline move.
+    pub fn end_position(&self) -> BitBoardPosition {
+        let mut position = self.start_position.clone();
+        for pgn_move in &self.moves {
+            let played_move = pgn_move.played_move;
+            position.make_move((played_move.start, played_move.end), played_move.promotion)
+                .expect("moves were replayed when the game was read");
+        }
+        position
+    }
+}
+
+#[derive(Debug)]
+pub enum PgnError {
+    Io(io::Error),
+    Syntax(String),
+    InvalidFen(FenError),
+    /// The FEN tag reads, but isn't a position a game could be played from.
+    InvalidPosition(Vec<PositionError>),
+    /// `ply` counts half moves from the start of the game, starting at 1.
+    IllegalMove { ply: usize, san: String, reason: SanError },
+    /// A move of a game being written that can't be played from the
+    /// position before it.
+    UnplayableMove { ply: usize, reason: MoveError },
+}
+
+impl fmt::Display for PgnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "Couldn't read PGN: {}", err),
+            Self::Syntax(reason) => write!(f, "Invalid PGN: {}", reason),
+            Self::InvalidFen(err) => write!(f, "Invalid FEN tag: {}", err),
+            Self::InvalidPosition(errors) => {
+                let reasons: Vec<String> = errors.iter().map(|err| err.to_string()).collect();
+                write!(f, "Invalid position in the FEN tag: {}", reasons.join(", "))
+            },
+            Self::IllegalMove { ply, san, reason } => write!(f, "Illegal move {} at ply {}: {}", san, ply, reason),
+            Self::UnplayableMove { ply, reason } => write!(f, "Move at ply {} can't be played: {}", ply, reason),
+        }
+    }
+}
+
+impl std::error::Error for PgnError {}
+
+impl From<io::Error> for PgnError {
+    fn from(err: io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Reads the games of a PGN file one at a time. A game that fails to parse
+/// or replay is reported and reading goes on with the next one.
+pub struct PgnReader<R: BufRead> {
+    input: R,
+    pending_line: Option<String>,
+    failed: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(input: R) -> Self {
+        PgnReader { input, pending_line: None, failed: false }
+    }
+
+    // collects lines up to the game's result token, or up to the next tag
+    // section when a game is missing one
+    fn next_game_text(&mut self) -> io::Result<Option<String>> {
+        let mut text = String::new();
+        let mut scanner = GameEndScanner::default();
+        let mut in_movetext = false;
+
+        loop {
+            let line = match self.pending_line.take() {
+                Some(line) => line,
+                None => {
+                    let mut line = String::new();
+                    if self.input.read_line(&mut line)? == 0 { break; }
+                    line
+                },
+            };
+
+            let trimmed = line.trim_start();
+            if !scanner.in_comment && trimmed.starts_with('[') {
+                if in_movetext {
+                    self.pending_line = Some(line);
+                    break;
+                }
+                text.push_str(&line);
+                continue;
+            }
+
+            if !trimmed.is_empty() && !trimmed.starts_with('%') {
+                in_movetext = true;
+            }
+            text.push_str(&line);
+            if scanner.ends_game(&line) { break; }
+        }
+
+        if text.trim().is_empty() { return Ok(None); }
+        Ok(Some(text))
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed { return None; }
+
+        match self.next_game_text() {
+            Ok(Some(text)) => Some(parse_game(&text)),
+            Ok(None) => None,
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err.into()))
+            },
+        }
+    }
+}
+
+/// Parses and replays a single game.
+pub fn parse_game(text: &str) -> Result<PgnGame, PgnError> {
+    let mut tags = vec![];
+    let mut position: Option<BitBoardPosition> = None;
+    let mut start_position = None;
+    let mut comment: Option<String> = None;
+    let mut moves: Vec<PgnMove> = vec![];
+    let mut result = None;
+    let mut variation_depth = 0;
+    let mut variation_start = 0;
+
+    for token in tokenize(text)? {
+        match token {
+            Token::Tag(name, value) => {
+                if position.is_some() {
+                    return Err(PgnError::Syntax(format!("tag {} appears after the movetext started", name)));
+                }
+                tags.push((name, value));
+            },
+            Token::VariationStart(offset) => {
+                if variation_depth == 0 { variation_start = offset + 1; }
+                variation_depth += 1;
+            },
+            Token::VariationEnd(offset) => {
+                match variation_depth {
+                    0 => return Err(PgnError::Syntax("')' without a variation to close".to_string())),
+                    1 => match moves.last_mut() {
+                        Some(last) => last.variations.push(text[variation_start..offset].trim().to_string()),
+                        None => return Err(PgnError::Syntax("variation before the first move".to_string())),
+                    },
+                    _ => (),
+                }
+                variation_depth -= 1;
+            },
+            _ if variation_depth > 0 => (),
+            Token::Comment(comment_text) => {
+                let mut comment_text = comment_text.trim().to_string();
+                let target = match moves.last_mut() {
+                    Some(last) => {
+                        if let Some((clock, rest)) = take_clock(&comment_text) {
+                            last.clock = Some(clock);
+                            comment_text = rest;
+                        }
+                        &mut last.comment
+                    },
+                    None => &mut comment,
+                };
+                if comment_text.is_empty() { continue; }
+                *target = Some(match target.take() {
+                    Some(previous) => format!("{} {}", previous, comment_text),
+                    None => comment_text,
+                });
+            },
+            Token::Nag(nag) => match moves.last_mut() {
+                Some(last) => last.nags.push(nag),
+                None => return Err(PgnError::Syntax(format!("${} before the first move", nag))),
+            },
+            Token::San(san) => {
+                if position.is_none() {
+                    let start = start_from_tags(&tags)?;
+                    start_position = Some(start.clone());
+                    position = Some(start);
+                }
+                let current = position.as_mut().unwrap();
+                let ply = moves.len() + 1;
+                let illegal = |reason| PgnError::IllegalMove { ply, san: san.to_string(), reason };
+
+                let played_move = Move::from_san(san, current).map_err(illegal)?;
+                current.make_move((played_move.start, played_move.end), played_move.promotion)
+                    .map_err(|reason| illegal(SanError::IllegalMove(san.to_string(), reason)))?;
+                moves.push(PgnMove { played_move, comment: None, nags: vec![], clock: None, variations: vec![] });
+            },
+            Token::Result(game_result) => {
+                result = Some(game_result);
+                break;
+            },
+        }
+    }
+
+    if variation_depth > 0 {
+        return Err(PgnError::Syntax("variation is never closed".to_string()));
+    }
+
+    let result = result
+        .or_else(|| tags.iter().find(|(name, _)| name == "Result").and_then(|(_, value)| GameResult::from_str(value).ok()))
+        .unwrap_or(GameResult::Unknown);
+    let start_position = match start_position {
+        Some(start_position) => start_position,
+        None => start_from_tags(&tags)?,
+    };
+
+    Ok(PgnGame { tags, start_position, comment, moves, result })
+}
+
+// splits a [%clk h:mm:ss] command off the rest of the comment, leaving the
+// comment as it was when the time can't be read. The writer may break the
+// line after `[%clk`, so any whitespace can follow it.
+fn take_clock(comment: &str) -> Option<(Duration, String)> {
+    let start = comment.find("[%clk")?;
+    let end = comment[start..].find(']')? + start;
+    let clock = parse_clock(comment[start + 5..end].trim())?;
+
+    let rest = format!("{} {}", comment[..start].trim(), comment[end + 1..].trim());
+    Some((clock, rest.trim().to_string()))
+}
+
+// hours, minutes and seconds as whole numbers, the seconds optionally with
+// a fraction, so there's no float to be negative, NaN or out of range
+fn parse_clock(text: &str) -> Option<Duration> {
+    let is_number = |part: &str| !part.is_empty() && part.bytes().all(|digit| digit.is_ascii_digit());
+
+    let (whole, fraction) = text.split_once('.').unwrap_or((text, "0"));
+    if !is_number(fraction) { return None; }
+
+    let parts: Vec<&str> = whole.split(':').collect();
+    if parts.len() > 3 { return None; }
+    let mut seconds: u64 = 0;
+    for part in parts {
+        if !is_number(part) { return None; }
+        seconds = seconds.checked_mul(60)?.checked_add(part.parse().ok()?)?;
+    }
+
+    // nanoseconds, past which digits are dropped
+    let nanos = format!("{:0<9}", &fraction[..fraction.len().min(9)]).parse().ok()?;
+    Some(Duration::new(seconds, nanos))
+}
+
+fn start_from_tags(tags: &[(String, String)]) -> Result<BitBoardPosition, PgnError> {
+    match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => {
+            let position = BitBoardPosition::from_fen(fen).map_err(PgnError::InvalidFen)?;
+            position.validate().map_err(PgnError::InvalidPosition)?;
+            Ok(position)
+        },
+        None => Ok(BitBoardPosition::from_position(&INITIAL_POSITION)),
+    }
+}
+
+enum Token<'a> {
+    Tag(String, String),
+    San(&'a str),
+    Nag(u8),
+    Comment(&'a str),
+    /// Byte offsets of the parentheses, to slice out the variation's text.
+    VariationStart(usize),
+    VariationEnd(usize),
+    Result(GameResult),
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token<'_>>, PgnError> {
+    let mut tokens = vec![];
+    let mut chars = text.char_indices().peekable();
+    let mut line_start = true;
+
+    while let Some((offset, symbol)) = chars.next() {
+        let at_line_start = line_start;
+        line_start = symbol == '\n';
+
+        match symbol {
+            _ if symbol.is_whitespace() => (),
+            // escaped lines are for other programs
+            '%' if at_line_start => {
+                for (_, skipped) in chars.by_ref() {
+                    if skipped == '\n' { line_start = true; break; }
+                }
+            },
+            '{' => {
+                let end = text[offset..].find('}')
+                    .ok_or_else(|| PgnError::Syntax("comment is never closed".to_string()))? + offset;
+                tokens.push(Token::Comment(&text[offset + 1..end]));
+                while chars.peek().is_some_and(|(next_offset, _)| *next_offset <= end) { chars.next(); }
+            },
+            ';' => {
+                let end = text[offset..].find('\n').map_or(text.len(), |end| end + offset);
+                tokens.push(Token::Comment(&text[offset + 1..end]));
+                while chars.peek().is_some_and(|(next_offset, _)| *next_offset < end) { chars.next(); }
+            },
+            '(' => tokens.push(Token::VariationStart(offset)),
+            ')' => tokens.push(Token::VariationEnd(offset)),
+            '[' => {
+                let end = tag_end(text, offset)?;
+                tokens.push(parse_tag(&text[offset + 1..end])?);
+                while chars.peek().is_some_and(|(next_offset, _)| *next_offset <= end) { chars.next(); }
+            },
+            _ => {
+                let rest = offset + symbol.len_utf8();
+                let end = text[rest..]
+                    .find(|next: char| next.is_whitespace() || "{}()[];$".contains(next))
+                    .map_or(text.len(), |end| end + rest);
+                while chars.peek().is_some_and(|(next_offset, _)| *next_offset < end) { chars.next(); }
+
+                let word = &text[offset..end];
+                if let Some(nag) = word.strip_prefix('$') {
+                    let nag = nag.parse().map_err(|_| PgnError::Syntax(format!("\"{}\" isn't a numeric annotation glyph", word)))?;
+                    tokens.push(Token::Nag(nag));
+                } else if let Ok(game_result) = GameResult::from_str(word) {
+                    tokens.push(Token::Result(game_result));
+                } else {
+                    // move numbers, "12." or "12...", may be written against the move
+                    let san = match word.find(|next: char| !next.is_ascii_digit()) {
+                        Some(number_end) if number_end > 0 && word[number_end..].starts_with('.') => word[number_end..].trim_start_matches('.'),
+                        _ => word,
+                    };
+                    if !san.is_empty() && !san.chars().all(|next| next.is_ascii_digit()) {
+                        tokens.push(Token::San(san));
+                    }
+                }
+            },
+        }
+    }
+    Ok(tokens)
+}
+
+// the closing bracket, skipping any inside the quoted value
+fn tag_end(text: &str, start: usize) -> Result<usize, PgnError> {
+    let mut in_quotes = false;
+    let mut escaped = false;
+    for (offset, symbol) in text[start..].char_indices() {
+        match symbol {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ']' if !in_quotes => return Ok(start + offset),
+            '\n' => break,
+            _ => (),
+        }
+    }
+    Err(PgnError::Syntax(format!("tag \"{}\" is never closed", text[start..].lines().next().unwrap_or(""))))
+}
+
+fn parse_tag(tag: &str) -> Result<Token<'_>, PgnError> {
+    let invalid = || PgnError::Syntax(format!("\"[{}]\" isn't a tag pair like [Event \"Casual game\"]", tag));
+
+    let tag = tag.trim();
+    let name_end = tag.find(char::is_whitespace).ok_or_else(invalid)?;
+    let name = &tag[..name_end];
+    let quoted = tag[name_end..].trim();
+    if name.is_empty() || quoted.len() < 2 || !quoted.starts_with('"') || !quoted.ends_with('"') {
+        return Err(invalid());
+    }
+
+    let mut value = String::new();
+    let mut escaped = false;
+    for symbol in quoted[1..quoted.len() - 1].chars() {
+        match symbol {
+            '\\' if !escaped => escaped = true,
+            _ => {
+                value.push(symbol);
+                escaped = false;
+            },
+        }
+    }
+    Ok(Token::Tag(name.to_string(), value))
+}
+
+// follows comments and variations across lines to spot the result token
+// that ends a game's movetext
+#[derive(Default)]
+struct GameEndScanner {
+    in_comment: bool,
+    variation_depth: usize,
+}
+
+impl GameEndScanner {
+    fn ends_game(&mut self, line: &str) -> bool {
+        if line.starts_with('%') { return false; }
+
+        let mut word = String::new();
+        for symbol in line.chars().chain(std::iter::once('\n')) {
+            if self.in_comment {
+                if symbol == '}' { self.in_comment = false; }
+                continue;
+            }
+            if symbol.is_whitespace() || "{;()".contains(symbol) {
+                if self.variation_depth == 0 && GameResult::from_str(&word).is_ok() {
+                    return true;
+                }
+                word.clear();
+            }
+            match symbol {
+                '{' => self.in_comment = true,
+                ';' => break,
+                '(' => self.variation_depth += 1,
+                ')' => self.variation_depth = self.variation_depth.saturating_sub(1),
+                _ if symbol.is_whitespace() => (),
+                _ => word.push(symbol),
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{parse_game, take_clock, PgnError, PgnReader};
+    use crate::definitions::{MoveError, Player, PositionError, Square};
+    use crate::san::SanError;
+
+    #[test]
+    fn tags() {
+        let game = parse_game(concat!(
+            "[Event \"Casual \\\"blitz\\\" game\"]\n",
+            "[Site \"[Somewhere]\"]\n",
+            "[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n",
+            "\n",
+            "1. e4 Kd7 1/2-1/2\n",
+        )).unwrap();
+        assert_eq!(game.tag("Event"), Some("Casual \"blitz\" game"));
+        assert_eq!(game.tag("Site"), Some("[Somewhere]"));
+        assert_eq!(game.tag("Round"), None);
+        assert_eq!(game.start_position.to_fen(), "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1");
+        assert_eq!(game.end_position().to_fen(), "8/3k4/8/8/4P3/8/8/4K3 w - - 1 2");
+
+        assert!(matches!(parse_game("[Event Casual]\n1. e4 *"), Err(PgnError::Syntax(_))));
+        assert!(matches!(parse_game("[FEN \"8/8 w - - 0 1\"]\n*"), Err(PgnError::InvalidFen(_))));
+        for movetext in ["*", "1. Kb1 *"] {
+            match parse_game(&format!("[FEN \"7r/8/8/8/8/8/8/K6K w - - 0 1\"]\n{}", movetext)) {
+                Err(PgnError::InvalidPosition(errors)) => assert_eq!(errors, vec![PositionError::TooManyKings(Player::White), PositionError::MissingKing(Player::Black)]),
+                other => panic!("{:?}", other),
+            }
+        }
+    }
+
+    #[test]
+    fn annotations() {
+        let game = parse_game("{Opening} 1. e4 $1 $14 {Best by test} e5 $2 2. Nf3 {[%clk 0:05:00]} *").unwrap();
+        assert_eq!(game.comment.as_deref(), Some("Opening"));
+        assert_eq!(game.moves[0].nags, vec![1, 14]);
+        assert_eq!(game.moves[0].comment.as_deref(), Some("Best by test"));
+        assert_eq!(game.moves[1].nags, vec![2]);
+        assert_eq!(game.moves[2].comment, None);
+        assert!(matches!(parse_game("$1 1. e4 *"), Err(PgnError::Syntax(_))));
+        assert!(matches!(parse_game("1. e4 {never closed *"), Err(PgnError::Syntax(_))));
+    }
+
+    #[test]
+    fn clocks() {
+        let clock = |comment: &str| take_clock(comment).map(|(clock, _)| clock);
+        assert_eq!(clock("[%clk 1:05:30]"), Some(Duration::from_secs(3930)));
+        assert_eq!(clock("[%clk 0:00:09.25]"), Some(Duration::from_millis(9250)));
+        assert_eq!(clock("[%clk 5:07]"), Some(Duration::from_secs(307)));
+        assert_eq!(take_clock("Good move [%clk 0:01:00] with time"), Some((Duration::from_secs(60), "Good move with time".to_string())));
+
+        for bad in ["NaN", "-1:00:00", "1e300", "inf", "1:2:3:4", "1::00", "0:00:01.", "0:00:01.-5", "99999999999999999999:00:00", ""] {
+            assert_eq!(take_clock(&format!("[%clk {}]", bad)), None, "{}", bad);
+        }
+        let game = parse_game("1. e4 {[%clk NaN]} e5 {Fast [%clk -1:00:00]} *").unwrap();
+        assert_eq!(game.moves[0].clock, None);
+        assert_eq!(game.moves[0].comment.as_deref(), Some("[%clk NaN]"));
+        assert_eq!(game.moves[1].comment.as_deref(), Some("Fast [%clk -1:00:00]"));
+    }
+
+    #[test]
+    fn nested_variations() {
+        let game = parse_game("1. e4 (1. d4 d5 (1... Nf6 2. c4) 2. c4) (1. Nf3) e5 (1... c5 {Sicilian}) 2. Nf3 *").unwrap();
+        assert_eq!(game.moves.len(), 3);
+        assert_eq!(game.moves[0].variations, vec!["1. d4 d5 (1... Nf6 2. c4) 2. c4", "1. Nf3"]);
+        assert_eq!(game.moves[1].variations, vec!["1... c5 {Sicilian}"]);
+        assert_eq!(game.moves[1].comment, None);
+
+        // moves in variations aren't replayed, so they may be illegal
+        assert!(parse_game("1. e4 (1. Ke2) e5 *").is_ok());
+        assert!(matches!(parse_game("1. e4 (1. d4 e5"), Err(PgnError::Syntax(_))));
+        assert!(matches!(parse_game("1. e4 ) e5 *"), Err(PgnError::Syntax(_))));
+        assert!(matches!(parse_game("(1. d4) 1. e4 *"), Err(PgnError::Syntax(_))));
+    }
+
+    #[test]
+    fn escaped_lines() {
+        let game = parse_game("1. e4\n% Nf6 is skipped, { and so is this\ne5 *").unwrap();
+        assert_eq!(game.moves.len(), 2);
+        assert_eq!(game.moves[1].played_move.end, Square::E5);
+
+        // only at the start of a line
+        assert!(matches!(parse_game("1. e4 % e5 *"), Err(PgnError::IllegalMove { ply: 2, .. })));
+    }
+
+    #[test]
+    fn illegal_move_reasons() {
+        match parse_game("1. e4 e5 2. Bc4 Nc6 3. Qh5 Nf6 4. Qxf7+ Kf7 *") {
+            Err(PgnError::IllegalMove { ply, san, reason }) => {
+                assert_eq!((ply, san.as_str()), (8, "Kf7"));
+                assert_eq!(reason, SanError::IllegalMove("Kf7".to_string(), MoveError::LeavesKingInCheck));
+            },
+            other => panic!("{:?}", other),
+        }
+        match parse_game("1. Qh5 *") {
+            Err(PgnError::IllegalMove { ply, reason, .. }) => {
+                assert_eq!(ply, 1);
+                assert_eq!(reason, SanError::IllegalMove("Qh5".to_string(), MoveError::BlockedPath));
+            },
+            other => panic!("{:?}", other),
+        }
+    }
+
+    #[test]
+    fn reading_on_after_a_bad_game() {
+        let text = concat!(
+            "[Event \"First\"]\n\n1. e4 e5 1-0\n\n",
+            "[Event \"Second\"]\n\n1. e4 e4 {a comment with 1-0 in it} 0-1\n\n",
+            "[Event \"Third\"]\n\n1. d4 (1. e4 1-0) d5 1/2-1/2\n\n",
+            "[Event \"No result\"]\n\n1. c4\n",
+            "[Event \"Last\"]\n\n1. Nf3 *\n",
+        );
+        let games: Vec<_> = PgnReader::new(text.as_bytes()).collect();
+        assert_eq!(games.len(), 5);
+        assert_eq!(games[0].as_ref().unwrap().tag("Event"), Some("First"));
+        assert!(matches!(games[1], Err(PgnError::IllegalMove { ply: 2, .. })));
+        assert_eq!(games[2].as_ref().unwrap().moves.len(), 2);
+        assert_eq!(games[3].as_ref().unwrap().moves.len(), 1);
+        assert_eq!(games[4].as_ref().unwrap().tag("Event"), Some("Last"));
+    }
+}
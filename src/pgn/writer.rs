@@ -0,0 +1,221 @@
+use std::time::Duration;
+
+use crate::bit_board::BitBoardPosition;
+use crate::definitions::{Player, INITIAL_POSITION};
+
+use super::{GameResult, PgnError, PgnGame};
+
+const SEVEN_TAG_ROSTER: [(&str, &str); 7] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+    ("Result", "*"),
+];
+
+// export format keeps movetext lines under 80 characters
+const LINE_LENGTH: usize = 79;
+
+impl PgnGame {
+    /// A game without moves or tags, to be filled in as it's played.
+    pub fn new(start_position: BitBoardPosition) -> Self {
+        PgnGame {
+            tags: vec![],
+            start_position,
+            comment: None,
+            moves: vec![],
+            result: GameResult::Unknown,
+        }
+    }
+
+    /// The game in PGN export format. The Seven Tag Roster always comes
+    /// first, with `?` for the tags that weren't set and `Result` taken from
+    /// `result`. A start position other than the initial one adds the
+    /// SetUp and FEN tags. Fails on the first move that can't be played.
+    pub fn to_pgn(&self) -> Result<String, PgnError> {
+        let mut pgn = String::new();
+
+        let result = self.result.to_string();
+        for (name, default) in SEVEN_TAG_ROSTER {
+            let value = match name {
+                "Result" => result.as_str(),
+                _ => self.tag(name).unwrap_or(default),
+            };
+            pgn.push_str(&tag_pair(name, value));
+        }
+
+        let start_fen = self.start_position.to_fen();
+        let is_custom_start = start_fen != INITIAL_POSITION.to_fen();
+        if is_custom_start {
+            pgn.push_str(&tag_pair("SetUp", "1"));
+            pgn.push_str(&tag_pair("FEN", &start_fen));
+        }
+
+        for (name, value) in &self.tags {
+            let is_written = SEVEN_TAG_ROSTER.iter().any(|(roster_name, _)| roster_name == name)
+                || (is_custom_start && (name == "SetUp" || name == "FEN"));
+            if !is_written {
+                pgn.push_str(&tag_pair(name, value));
+            }
+        }
+        pgn.push('\n');
+
+        let mut movetext = MovetextWriter::default();
+        if let Some(comment) = &self.comment {
+            movetext.push(&braced(comment));
+        }
+
+        let mut position = self.start_position.clone();
+        let mut needs_move_number = true;
+        for (index, pgn_move) in self.moves.iter().enumerate() {
+            let played_move = pgn_move.played_move;
+            let move_number = position.fullmove_number();
+            let after = position.play_move((played_move.start, played_move.end), played_move.promotion)
+                .map_err(|reason| PgnError::UnplayableMove { ply: index + 1, reason })?;
+
+            match position.to_play {
+                Player::White => movetext.push(&format!("{}.", move_number)),
+                Player::Black if needs_move_number => movetext.push(&format!("{}...", move_number)),
+                Player::Black => (),
+            }
+            movetext.push(&played_move.to_san(&position));
+            needs_move_number = false;
+
+            for nag in &pgn_move.nags {
+                movetext.push(&format!("${}", nag));
+            }
+
+            let comment = match (pgn_move.clock, &pgn_move.comment) {
+                (Some(clock), Some(comment)) => Some(braced(&format!("{} {}", clock_command(clock), comment))),
+                (Some(clock), None) => Some(braced(&clock_command(clock))),
+                (None, Some(comment)) => Some(braced(comment)),
+                (None, None) => None,
+            };
+            if let Some(comment) = comment {
+                movetext.push(&comment);
+                needs_move_number = true;
+            }
+
+            for variation in &pgn_move.variations {
+                movetext.push(&format!("({})", variation));
+                needs_move_number = true;
+            }
+
+            position = after;
+        }
+
+        movetext.push(&result);
+        pgn.push_str(&movetext.finish());
+        Ok(pgn)
+    }
+}
+
+fn tag_pair(name: &str, value: &str) -> String {
+    format!("[{} \"{}\"]\n", name, value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// a `}` would end the comment early, PGN has no way to escape it
+fn braced(comment: &str) -> String {
+    format!("{{{}}}", comment.replace('}', ")"))
+}
+
+// the fraction of a second only when there is one, without trailing zeros
+fn clock_command(clock: Duration) -> String {
+    let seconds = clock.as_secs();
+    let fraction = match clock.subsec_nanos() {
+        0 => String::new(),
+        nanos => format!(".{:09}", nanos).trim_end_matches('0').to_string(),
+    };
+    format!("[%clk {}:{:02}:{:02}{}]", seconds / 3600, seconds / 60 % 60, seconds % 60, fraction)
+}
+
+#[derive(Default)]
+struct MovetextWriter {
+    text: String,
+    line_length: usize,
+}
+
+impl MovetextWriter {
+    // long comments and variations are broken between words
+    fn push(&mut self, token: &str) {
+        for word in token.split_whitespace() {
+            if self.line_length > 0 && self.line_length + 1 + word.len() > LINE_LENGTH {
+                self.text.push('\n');
+                self.line_length = 0;
+            } else if self.line_length > 0 {
+                self.text.push(' ');
+                self.line_length += 1;
+            }
+            self.text.push_str(word);
+            self.line_length += word.len();
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.text.push('\n');
+        self.text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use crate::bit_board::BitBoardPosition;
+    use crate::definitions::{Move, MoveError, Piece, PieceType, Player, Square, INITIAL_POSITION};
+    use crate::pgn::{parse_game, PgnError, PgnGame, PgnMove};
+
+    #[test]
+    fn round_trip() {
+        let text = "[Event \"Club \\\"open\\\"\"]\n\n{Start} 1. e4 $1 {Best [%clk 0:05:00]} e5 (1... c5) 2. Nf3 Nc6 1-0";
+        let game = parse_game(text).unwrap();
+        let pgn = game.to_pgn().unwrap();
+        assert!(pgn.starts_with("[Event \"Club \\\"open\\\"\"]\n[Site \"?\"]\n"));
+        assert!(pgn.ends_with("\n\n{Start} 1. e4 $1 {[%clk 0:05:00] Best} 1... e5 (1... c5) 2. Nf3 Nc6 1-0\n"));
+
+        let reread = parse_game(&pgn).unwrap();
+        assert_eq!(reread.moves, game.moves);
+        assert_eq!(reread.tag("Event"), Some("Club \"open\""));
+    }
+
+    #[test]
+    fn fractional_clocks() {
+        let mut game = parse_game("1. e4 e5 2. Nf3 *").unwrap();
+        let clocks = [Duration::new(3725, 500_000_000), Duration::from_millis(59_050), Duration::new(0, 1)];
+        for (pgn_move, clock) in game.moves.iter_mut().zip(clocks) {
+            pgn_move.clock = Some(clock);
+        }
+
+        let pgn = game.to_pgn().unwrap();
+        let movetext = pgn.split_whitespace().collect::<Vec<_>>().join(" ");
+        assert!(movetext.ends_with("1. e4 {[%clk 1:02:05.5]} 1... e5 {[%clk 0:00:59.05]} 2. Nf3 {[%clk 0:00:00.000000001]} *"));
+        let reread = parse_game(&pgn).unwrap();
+        assert_eq!(reread.moves.iter().map(|pgn_move| pgn_move.clock).collect::<Vec<_>>(), clocks.map(Some));
+    }
+
+    #[test]
+    fn closing_braces_in_comments() {
+        let mut game = parse_game("1. e4 e5 *").unwrap();
+        game.comment = Some("A {short} game".to_string());
+        game.moves[0].comment = Some("} 2. Qh5".to_string());
+
+        let pgn = game.to_pgn().unwrap();
+        assert!(pgn.contains("{A {short) game} 1. e4 {) 2. Qh5} 1... e5 *"));
+        let reread = parse_game(&pgn).unwrap();
+        assert_eq!(reread.moves.len(), 2);
+        assert_eq!(reread.moves[0].comment.as_deref(), Some(") 2. Qh5"));
+    }
+
+    #[test]
+    fn unplayable_moves() {
+        let mut game = PgnGame::new(BitBoardPosition::from_position(&INITIAL_POSITION));
+        let pawn = Piece { piece_type: PieceType::Pawn, owner: Player::White };
+        for (start, end) in [(Square::E2, Square::E4), (Square::E4, Square::E5)] {
+            let played_move = Move { piece: pawn, start, end, promotion: None };
+            game.moves.push(PgnMove { played_move, comment: None, nags: vec![], clock: None, variations: vec![] });
+        }
+        assert!(matches!(game.to_pgn(), Err(PgnError::UnplayableMove { ply: 2, reason: MoveError::NotYourPiece })));
+    }
+}
@@ -0,0 +1,298 @@
+use crate::bit_board::BitBoardPosition;
+use crate::definitions::{File, Piece, PieceType, Player, Rank, SimplePosition, Square};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RenderStyle {
+    /// FEN letters, `.` for empty squares.
+    Ascii,
+    /// Outlined glyphs for White, filled ones for Black.
+    Unicode,
+    /// Filled glyphs coloured by owner on coloured squares, for terminals
+    /// that understand 256-colour escape codes.
+    Ansi,
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderOptions {
+    /// The player whose pieces are at the bottom.
+    pub orientation: Player,
+    /// Files below the board and ranks to its left.
+    pub labels: bool,
+    pub style: RenderStyle,
+    /// The start and end squares to highlight, `[P]` without colours.
+    pub last_move: Option<(Square, Square)>,
+    /// Highlights the king of the side to move when it's in check, `(K)`
+    /// without colours.
+    pub highlight_check: bool,
+    /// Side to move, castling rights, en passant square and move number
+    /// next to the board.
+    pub side_panel: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        RenderOptions {
+            orientation: Player::White,
+            labels: true,
+            style: RenderStyle::Unicode,
+            last_move: None,
+            highlight_check: true,
+            side_panel: false,
+        }
+    }
+}
+
+const RESET: &str = "\x1b[0m";
+const LIGHT_SQUARE: &str = "\x1b[48;5;180m";
+const DARK_SQUARE: &str = "\x1b[48;5;137m";
+const LAST_MOVE_SQUARE: &str = "\x1b[48;5;143m";
+const CHECK_SQUARE: &str = "\x1b[48;5;160m";
+const WHITE_PIECE: &str = "\x1b[38;5;231m";
+const BLACK_PIECE: &str = "\x1b[38;5;16m";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Highlight {
+    None,
+    LastMove,
+    Check,
+}
+
+impl SimplePosition {
+    /// The board as text, a line per rank with the side panel, if any, to
+    /// the right of the top ranks. Trailing spaces are trimmed.
+    pub fn render(&self, options: &RenderOptions) -> String {
+        let checked_king = if options.highlight_check { checked_king(self) } else { None };
+
+        let mut ranks: Vec<Rank> = Rank::all().collect();
+        let mut files: Vec<File> = File::all().collect();
+        match options.orientation {
+            Player::White => ranks.reverse(),
+            Player::Black => files.reverse(),
+        }
+
+        let panel = if options.side_panel { side_panel(self) } else { vec![] };
+
+        let mut lines = vec![];
+        for (row, rank) in ranks.iter().enumerate() {
+            let mut line = String::new();
+            if options.labels {
+                line.push_str(&format!("{} ", rank));
+            }
+            for file in &files {
+                let square = Square::from_coordinates(*file, *rank);
+                let highlight = if Some(square) == checked_king {
+                    Highlight::Check
+                } else if options.last_move.is_some_and(|(start, end)| square == start || square == end) {
+                    Highlight::LastMove
+                } else {
+                    Highlight::None
+                };
+                line.push_str(&cell(self.board[square as usize].piece(), square, highlight, options.style));
+            }
+            if let Some(panel_line) = panel.get(row) {
+                line.push_str("   ");
+                line.push_str(panel_line);
+            }
+            lines.push(line);
+        }
+
+        if options.labels {
+            let mut line = "  ".to_string();
+            for file in &files {
+                line.push_str(&format!(" {} ", file));
+            }
+            lines.push(line);
+        }
+
+        lines.iter().map(|line| line.trim_end()).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// The board with White at the bottom, see `render` for other layouts.
+impl ToString for SimplePosition {
+    fn to_string(&self) -> String {
+        let mut result = self.render(&RenderOptions::default());
+        result.push_str("\n\n");
+        result.push_str(match self.to_play { Player::White => "White", Player::Black => "Black" });
+        result.push_str(" to play");
+        result.push_str("\n\n");
+        result
+    }
+}
+
+impl BitBoardPosition {
+    /// See `SimplePosition::render`.
+    pub fn render(&self, options: &RenderOptions) -> String {
+        self.to_position().render(options)
+    }
+}
+
+fn checked_king(position: &SimplePosition) -> Option<Square> {
+    let bit_board_position = BitBoardPosition::from_position(position);
+    if bit_board_position.checkers() == 0 {
+        return None;
+    }
+    let king = Piece { piece_type: PieceType::King, owner: position.to_play };
+    Square::all().find(|square| position.board[*square as usize].piece() == Some(king))
+}
+
+// three characters wide, plus escape codes in the ANSI style
+fn cell(piece: Option<Piece>, square: Square, highlight: Highlight, style: RenderStyle) -> String {
+    if style == RenderStyle::Ansi {
+        let background = match highlight {
+            Highlight::Check => CHECK_SQUARE,
+            Highlight::LastMove => LAST_MOVE_SQUARE,
+            Highlight::None if (square.file() as u8 + square.rank() as u8).is_multiple_of(2) => DARK_SQUARE,
+            Highlight::None => LIGHT_SQUARE,
+        };
+        let symbol = match piece {
+            Some(piece) => {
+                let colour = if piece.owner == Player::White { WHITE_PIECE } else { BLACK_PIECE };
+                format!("{}{}", colour, Piece { piece_type: piece.piece_type, owner: Player::Black }.to_string())
+            },
+            None => " ".to_string(),
+        };
+        return format!("{} {} {}", background, symbol, RESET);
+    }
+
+    let symbol = match (piece, style) {
+        (Some(piece), RenderStyle::Ascii) => piece.fen_char().to_string(),
+        (Some(piece), _) => piece.to_string(),
+        (None, _) => ".".to_string(),
+    };
+    match highlight {
+        Highlight::Check => format!("({})", symbol),
+        Highlight::LastMove => format!("[{}]", symbol),
+        Highlight::None => format!(" {} ", symbol),
+    }
+}
+
+fn side_panel(position: &SimplePosition) -> Vec<String> {
+    let fen = position.to_fen();
+    let castling = fen.split(' ').nth(2).unwrap();
+
+    vec![
+        format!("{:?} to play", position.to_play),
+        format!("Move {}", position.fullmove_number),
+        format!("Castling: {}", castling),
+        format!("En passant: {}", position.en_passant_square),
+        format!("Halfmove clock: {}", position.halfmove_clock),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RenderOptions, RenderStyle, BLACK_PIECE, CHECK_SQUARE, DARK_SQUARE, LAST_MOVE_SQUARE, LIGHT_SQUARE, RESET, WHITE_PIECE};
+    use crate::bit_board::BitBoardPosition;
+    use crate::definitions::{Player, Square};
+
+    fn render(fen: &str, options: RenderOptions) -> String {
+        BitBoardPosition::from_fen(fen).unwrap().render(&options)
+    }
+
+    const PAWN_ENDING: &str = "4k3/8/8/8/8/8/4P3/4K3 w - - 0 1";
+
+    #[test]
+    fn ascii_and_unicode() {
+        let ascii = render(PAWN_ENDING, RenderOptions { style: RenderStyle::Ascii, ..RenderOptions::default() });
+        assert_eq!(ascii, concat!(
+            "8  .  .  .  .  k  .  .  .\n",
+            "7  .  .  .  .  .  .  .  .\n",
+            "6  .  .  .  .  .  .  .  .\n",
+            "5  .  .  .  .  .  .  .  .\n",
+            "4  .  .  .  .  .  .  .  .\n",
+            "3  .  .  .  .  .  .  .  .\n",
+            "2  .  .  .  .  P  .  .  .\n",
+            "1  .  .  .  .  K  .  .  .\n",
+            "   a  b  c  d  e  f  g  h",
+        ));
+
+        let unicode = render(PAWN_ENDING, RenderOptions { labels: false, ..RenderOptions::default() });
+        assert_eq!(unicode, concat!(
+            " .  .  .  .  ♚  .  .  .\n",
+            " .  .  .  .  .  .  .  .\n",
+            " .  .  .  .  .  .  .  .\n",
+            " .  .  .  .  .  .  .  .\n",
+            " .  .  .  .  .  .  .  .\n",
+            " .  .  .  .  .  .  .  .\n",
+            " .  .  .  .  ♙  .  .  .\n",
+            " .  .  .  .  ♔  .  .  .",
+        ));
+    }
+
+    #[test]
+    fn ansi() {
+        let options = RenderOptions { style: RenderStyle::Ansi, labels: false, ..RenderOptions::default() };
+        let board = render("8/8/8/8/8/8/8/k6K w - - 0 1", options.clone());
+        let lines: Vec<&str> = board.lines().collect();
+        assert_eq!(lines.len(), 8);
+
+        let empty = |background: &str| format!("{}   {}", background, RESET);
+        let light_rank = [LIGHT_SQUARE, DARK_SQUARE].repeat(4).iter().map(|background| empty(background)).collect::<String>();
+        assert_eq!(lines[0], light_rank);
+
+        // both kings use the filled glyph, coloured by owner
+        let mut first_rank = format!("{} {}♚ {}", DARK_SQUARE, BLACK_PIECE, RESET);
+        for background in [LIGHT_SQUARE, DARK_SQUARE].repeat(3) {
+            first_rank.push_str(&empty(background));
+        }
+        first_rank.push_str(&format!("{} {}♚ {}", LIGHT_SQUARE, WHITE_PIECE, RESET));
+        assert_eq!(lines[7], first_rank);
+
+        let highlighted = render(
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            RenderOptions { last_move: Some((Square::D8, Square::H4)), ..options },
+        );
+        let lines: Vec<&str> = highlighted.lines().collect();
+        assert!(lines[7].contains(&format!("{} {}♚ {}", CHECK_SQUARE, WHITE_PIECE, RESET)));
+        assert!(lines[4].ends_with(&format!("{} {}♛ {}", LAST_MOVE_SQUARE, BLACK_PIECE, RESET)));
+        assert!(lines[0].contains(&empty(LAST_MOVE_SQUARE)));
+    }
+
+    #[test]
+    fn black_orientation_with_highlights_and_side_panel() {
+        let options = RenderOptions {
+            orientation: Player::Black,
+            style: RenderStyle::Ascii,
+            last_move: Some((Square::D8, Square::H4)),
+            side_panel: true,
+            ..RenderOptions::default()
+        };
+        let fools_mate = "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3";
+        assert_eq!(render(fools_mate, options.clone()), concat!(
+            "1  R  N  B (K) Q  B  N  R    White to play\n",
+            "2  P  .  .  P  P  P  P  P    Move 3\n",
+            "3  .  .  P  .  .  .  .  .    Castling: KQkq\n",
+            "4 [q] P  .  .  .  .  .  .    En passant: -\n",
+            "5  .  .  .  p  .  .  .  .    Halfmove clock: 1\n",
+            "6  .  .  .  .  .  .  .  .\n",
+            "7  p  p  p  .  p  p  p  p\n",
+            "8  r  n  b  k [.] b  n  r\n",
+            "   h  g  f  e  d  c  b  a",
+        ));
+
+        let without_check = RenderOptions { highlight_check: false, last_move: None, ..options };
+        let board = render(fools_mate, without_check);
+        assert!(board.starts_with("1  R  N  B  K  Q  B  N  R    White to play\n"));
+        assert!(board.contains("\n4  q  P  .  .  .  .  .  .    En passant: -\n"));
+    }
+
+    #[test]
+    fn side_panel() {
+        let options = RenderOptions { style: RenderStyle::Ascii, labels: false, side_panel: true, ..RenderOptions::default() };
+        let board = render("r3k3/8/8/3pP3/8/8/8/4K2R w Kq d6 0 12", options);
+        let panel: Vec<&str> = board.lines().take(5).map(|line| line[24..].trim_start()).collect();
+        assert_eq!(panel, vec!["White to play", "Move 12", "Castling: Kq", "En passant: d6", "Halfmove clock: 0"]);
+
+        let options = RenderOptions { style: RenderStyle::Ascii, side_panel: true, ..RenderOptions::default() };
+        assert!(render("4k3/8/8/8/8/8/8/4K3 b - - 7 40", options).contains("Castling: -"));
+    }
+
+    #[test]
+    fn to_string() {
+        let position = BitBoardPosition::from_fen(PAWN_ENDING).unwrap().to_position();
+        let text = position.to_string();
+        assert!(text.starts_with("8  .  .  .  .  ♚  .  .  .\n"));
+        assert!(text.ends_with("   a  b  c  d  e  f  g  h\n\nWhite to play\n\n"));
+    }
+}
@@ -0,0 +1,348 @@
+use std::fmt;
+
+use crate::bit_board::BitBoardPosition;
+use crate::definitions::{File, Move, MoveError, Piece, PieceType, Player, Rank, Square};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SanError {
+    InvalidSyntax(String),
+    NoMatchingMove(String),
+    AmbiguousMove(String),
+    /// The notation names a move one of the side to move's pieces could
+    /// make on an empty board, with the reason it can't be played here.
+    IllegalMove(String, MoveError),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSyntax(san) => write!(f, "\"{}\" isn't a move in algebraic notation", san),
+            Self::NoMatchingMove(san) => write!(f, "{} isn't a legal move here", san),
+            Self::AmbiguousMove(san) => write!(f, "{} could be more than one move, name the file or rank it starts from", san),
+            Self::IllegalMove(san, reason) => write!(f, "{} isn't a legal move here: {}", san, reason),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+impl Move {
+    /// Resolves a move in Standard Algebraic Notation against the position it
+    /// is played from. Check and mate markers and annotations such as `!?`
+    /// are accepted but not required, as are `0-0` for castling and a
+    /// promotion without the `=`.
+    pub fn from_san(san: &str, position: &BitBoardPosition) -> Result<Self, SanError> {
+        let notation = Notation::parse(san, position.to_play)?;
+
+        let mut candidates = position.legal_moves().into_iter().filter(|legal_move| notation.allows(legal_move));
+
+        match (candidates.next(), candidates.next()) {
+            (Some(found), None) => Ok(found),
+            (None, _) => match notation.rejection(position) {
+                Some(reason) => Err(SanError::IllegalMove(san.to_string(), reason)),
+                None => Err(SanError::NoMatchingMove(san.to_string())),
+            },
+            (Some(_), Some(_)) => Err(SanError::AmbiguousMove(san.to_string())),
+        }
+    }
+
+    /// The move in Standard Algebraic Notation, `position` being the one it
+    /// is played from. Only as much of the start square is named as needed
+    /// to tell it apart from other legal moves.
+    pub fn to_san(self, position: &BitBoardPosition) -> String {
+        let mut san = String::new();
+
+        if self.is_castle_attempt() {
+            san.push_str(if self.end as u8 > self.start as u8 { "O-O" } else { "O-O-O" });
+        } else {
+            let is_capture = position.piece_at(self.end).is_some()
+                || (self.piece.piece_type == PieceType::Pawn && self.start.file() != self.end.file());
+
+            if self.piece.piece_type == PieceType::Pawn {
+                if is_capture {
+                    san.push_str(&self.start.file().to_string());
+                }
+            } else {
+                san.push(Piece { piece_type: self.piece.piece_type, owner: Player::White }.fen_char());
+                san.push_str(&self.disambiguation(position));
+            }
+
+            if is_capture {
+                san.push('x');
+            }
+            san.push_str(&self.end.to_string());
+
+            if let Some(promotion) = self.promotion {
+                san.push('=');
+                san.push(Piece { piece_type: promotion, owner: Player::White }.fen_char());
+            }
+        }
+
+        if let Ok(after) = position.play_move((self.start, self.end), self.promotion) {
+            if after.checkers() != 0 {
+                san.push(if after.legal_moves().is_empty() { '#' } else { '+' });
+            }
+        }
+
+        san
+    }
+
+    fn disambiguation(&self, position: &BitBoardPosition) -> String {
+        let rivals: Vec<Move> = position.legal_moves()
+            .into_iter()
+            .filter(|legal_move| legal_move.piece == self.piece && legal_move.end == self.end && legal_move.start != self.start)
+            .collect();
+
+        if rivals.is_empty() {
+            return String::new();
+        }
+        if rivals.iter().all(|rival| rival.start.file() != self.start.file()) {
+            return self.start.file().to_string();
+        }
+        if rivals.iter().all(|rival| rival.start.rank() != self.start.rank()) {
+            return self.start.rank().to_string();
+        }
+        self.start.to_string()
+    }
+}
+
+// what the notation says about a move before looking at the position
+struct Notation {
+    piece_type: PieceType,
+    end: Square,
+    from_file: Option<File>,
+    from_rank: Option<Rank>,
+    promotion: Option<PieceType>,
+    is_capture: bool,
+    is_castle: bool,
+}
+
+impl Notation {
+    fn parse(san: &str, to_play: Player) -> Result<Self, SanError> {
+        let invalid = || SanError::InvalidSyntax(san.to_string());
+        let notation = san.trim().trim_end_matches(['+', '#', '!', '?']);
+
+        let king_start = match to_play {
+            Player::White => Square::E1,
+            Player::Black => Square::E8,
+        };
+        let castle_end = match notation {
+            "O-O" | "0-0" => Some(king_start as u8 + 2),
+            "O-O-O" | "0-0-0" => Some(king_start as u8 - 2),
+            _ => None,
+        };
+        if let Some(end) = castle_end {
+            return Ok(Notation {
+                piece_type: PieceType::King,
+                end: Square::try_from(end).unwrap(),
+                from_file: Some(king_start.file()),
+                from_rank: Some(king_start.rank()),
+                promotion: None,
+                is_capture: false,
+                is_castle: true,
+            });
+        }
+
+        let mut symbols: Vec<char> = notation.chars().collect();
+
+        let piece_type = match symbols.first() {
+            Some(symbol) if "NBRQK".contains(*symbol) => {
+                let piece_type = Piece::from_fen_char(*symbol).unwrap().piece_type;
+                symbols.remove(0);
+                piece_type
+            },
+            _ => PieceType::Pawn,
+        };
+
+        let mut promotion = None;
+        if piece_type == PieceType::Pawn {
+            if let Some(symbol) = symbols.last().filter(|symbol| "NBRQ".contains(**symbol)) {
+                promotion = Some(Piece::from_fen_char(*symbol).unwrap().piece_type);
+                symbols.pop();
+                if symbols.last() == Some(&'=') {
+                    symbols.pop();
+                }
+            }
+        }
+
+        if symbols.len() < 2 { return Err(invalid()); }
+        let end = match (symbols[symbols.len() - 2], symbols[symbols.len() - 1]) {
+            (file @ 'a'..='h', rank) => Rank::from_char(rank).map(|rank| Square::from_coordinates(File::from_char(file).unwrap(), rank)),
+            _ => None,
+        }.ok_or_else(invalid)?;
+        symbols.truncate(symbols.len() - 2);
+
+        let is_capture = symbols.last() == Some(&'x');
+        if is_capture {
+            symbols.pop();
+        }
+
+        let mut from_file = None;
+        let mut from_rank = None;
+        for symbol in symbols {
+            match symbol {
+                'a'..='h' if from_file.is_none() && from_rank.is_none() => from_file = File::from_char(symbol),
+                '1'..='8' if from_rank.is_none() => from_rank = Rank::from_char(symbol),
+                _ => return Err(invalid()),
+            }
+        }
+
+        Ok(Notation { piece_type, end, from_file, from_rank, promotion, is_capture, is_castle: false })
+    }
+
+    fn allows(&self, legal_move: &Move) -> bool {
+        legal_move.piece.piece_type == self.piece_type
+            && legal_move.end == self.end
+            && legal_move.promotion == self.promotion
+            && legal_move.is_castle_attempt() == self.is_castle
+            && self.matches_start(legal_move.start)
+            // a pawn changing file captures, which has to name the file it leaves
+            && (self.piece_type != PieceType::Pawn
+                || legal_move.start.file() == self.end.file()
+                || (self.from_file.is_some() && self.is_capture))
+    }
+
+    fn matches_start(&self, start: Square) -> bool {
+        self.from_file.is_none_or(|file| start.file() == file)
+            && self.from_rank.is_none_or(|rank| start.rank() == rank)
+    }
+
+    // the reason make_move gives for the pieces that could make the move on
+    // an empty board, when they all give the same one
+    fn rejection(&self, position: &BitBoardPosition) -> Option<MoveError> {
+        let piece = Piece { piece_type: self.piece_type, owner: position.to_play };
+        // pawns only change file when capturing, which names the start file
+        let from_file = match self.piece_type {
+            PieceType::Pawn => self.from_file.or(Some(self.end.file())),
+            _ => self.from_file,
+        };
+
+        let mut reasons = Square::all()
+            .filter(|start| position.piece_at(*start) == Some(piece))
+            .filter(|start| self.matches_start(*start) && from_file.is_none_or(|file| start.file() == file))
+            .filter_map(|start| position.play_move((start, self.end), self.promotion).err())
+            .filter(|reason| !matches!(reason, MoveError::IllegalPieceMove(_)));
+
+        let reason = reasons.next()?;
+        reasons.all(|other| other == reason).then_some(reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SanError;
+    use crate::bit_board::BitBoardPosition;
+    use crate::definitions::{Move, MoveError, PieceType, Square, INITIAL_POSITION};
+
+    fn position(fen: &str) -> BitBoardPosition {
+        BitBoardPosition::from_fen(fen).unwrap()
+    }
+
+    fn san(position: &BitBoardPosition, start: Square, end: Square, promotion: Option<PieceType>) -> String {
+        position.legal_moves()
+            .into_iter()
+            .find(|legal_move| legal_move.start == start && legal_move.end == end && legal_move.promotion == promotion)
+            .unwrap()
+            .to_san(position)
+    }
+
+    fn squares(position: &BitBoardPosition, san: &str) -> (Square, Square, Option<PieceType>) {
+        let found = Move::from_san(san, position).unwrap();
+        (found.start, found.end, found.promotion)
+    }
+
+    #[test]
+    fn disambiguation() {
+        let files = position("4k3/8/8/8/8/3R4/8/R4R1K w - - 0 1");
+        assert_eq!(san(&files, Square::A1, Square::D1, None), "Rad1");
+        assert_eq!(san(&files, Square::F1, Square::D1, None), "Rfd1");
+        assert_eq!(squares(&files, "Rad1"), (Square::A1, Square::D1, None));
+        assert_eq!(squares(&files, "Rdd1"), (Square::D3, Square::D1, None));
+        assert_eq!(Move::from_san("Rd1", &files), Err(SanError::AmbiguousMove("Rd1".to_string())));
+
+        let ranks = position("4k3/8/8/8/8/3R4/8/3R3K w - - 0 1");
+        assert_eq!(san(&ranks, Square::D1, Square::D2, None), "R1d2");
+        assert_eq!(san(&ranks, Square::D3, Square::D2, None), "R3d2");
+        assert_eq!(squares(&ranks, "R1d2"), (Square::D1, Square::D2, None));
+        assert_eq!(Move::from_san("Rdd2", &ranks), Err(SanError::AmbiguousMove("Rdd2".to_string())));
+
+        let both = position("1k6/8/8/8/4Q2Q/8/8/K6Q w - - 0 1");
+        assert_eq!(san(&both, Square::H4, Square::E1, None), "Qh4e1");
+        assert_eq!(san(&both, Square::E4, Square::E1, None), "Qee1");
+        assert_eq!(san(&both, Square::H1, Square::E1, None), "Q1e1");
+        assert_eq!(squares(&both, "Qh4xe1"), (Square::H4, Square::E1, None));
+        for ambiguous in ["Qe1", "Qhe1", "Q4e1"] {
+            assert_eq!(Move::from_san(ambiguous, &both), Err(SanError::AmbiguousMove(ambiguous.to_string())));
+        }
+    }
+
+    #[test]
+    fn promotions() {
+        let promotion = position("7k/4P3/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(san(&promotion, Square::E7, Square::E8, Some(PieceType::Queen)), "e8=Q+");
+        assert_eq!(san(&promotion, Square::E7, Square::E8, Some(PieceType::Knight)), "e8=N");
+        for notation in ["e8=Q+", "e8=Q", "e8Q"] {
+            assert_eq!(squares(&promotion, notation), (Square::E7, Square::E8, Some(PieceType::Queen)));
+        }
+        assert_eq!(Move::from_san("e8", &promotion), Err(SanError::IllegalMove("e8".to_string(), MoveError::PromotionRequired)));
+    }
+
+    #[test]
+    fn castling() {
+        let castling = position("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        assert_eq!(san(&castling, Square::E1, Square::G1, None), "O-O");
+        assert_eq!(san(&castling, Square::E1, Square::C1, None), "O-O-O");
+        assert_eq!(squares(&castling, "O-O"), squares(&castling, "0-0"));
+        assert_eq!(squares(&castling, "0-0-0"), (Square::E1, Square::C1, None));
+
+        let no_rights = position("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1");
+        assert_eq!(Move::from_san("O-O", &no_rights), Err(SanError::IllegalMove("O-O".to_string(), MoveError::CastlingNotAllowed)));
+    }
+
+    #[test]
+    fn check_and_mate() {
+        let fools_mate = position("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq g3 0 2");
+        assert_eq!(san(&fools_mate, Square::D8, Square::H4, None), "Qh4#");
+        assert_eq!(squares(&fools_mate, "Qh4#"), squares(&fools_mate, "Qh4"));
+    }
+
+    #[test]
+    fn errors() {
+        let initial = BitBoardPosition::from_position(&INITIAL_POSITION);
+        assert_eq!(Move::from_san("Ne4", &initial), Err(SanError::NoMatchingMove("Ne4".to_string())));
+        assert_eq!(Move::from_san("e5", &initial), Err(SanError::NoMatchingMove("e5".to_string())));
+        assert_eq!(Move::from_san("Zz9", &initial), Err(SanError::InvalidSyntax("Zz9".to_string())));
+        assert_eq!(Move::from_san("", &initial), Err(SanError::InvalidSyntax("".to_string())));
+
+        // a pawn capture has to say so and name the file it comes from
+        let capture = position("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1");
+        assert_eq!(squares(&capture, "exd5"), (Square::E4, Square::D5, None));
+        for incomplete in ["d5", "xd5", "ed5"] {
+            assert_eq!(Move::from_san(incomplete, &capture), Err(SanError::NoMatchingMove(incomplete.to_string())));
+        }
+    }
+
+    #[test]
+    fn illegal_move_reasons() {
+        let illegal = |fen: &str, san: &str| match Move::from_san(san, &position(fen)) {
+            Err(SanError::IllegalMove(_, reason)) => Some(reason),
+            _ => None,
+        };
+
+        let initial = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_eq!(illegal(initial, "Bc4"), Some(MoveError::BlockedPath));
+        assert_eq!(illegal(initial, "Ra3"), Some(MoveError::BlockedPath));
+        assert_eq!(illegal(initial, "Nd2"), Some(MoveError::OccupiedByOwnPiece));
+        assert_eq!(illegal(initial, "exd3"), Some(MoveError::PawnCaptureWithoutTarget));
+        assert_eq!(illegal(initial, "O-O"), Some(MoveError::CastlingBlocked));
+
+        let pinned = "4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1";
+        assert_eq!(illegal(pinned, "Nf4"), Some(MoveError::LeavesKingInCheck));
+        assert_eq!(illegal("4k3/8/8/8/8/4p3/4P3/4K3 w - - 0 1", "e3"), Some(MoveError::PawnCannotCaptureForward));
+
+        // no piece that could make the move, or pieces that fail differently
+        assert_eq!(illegal(initial, "Ne4"), None);
+        assert_eq!(illegal(initial, "e5"), None);
+        assert_eq!(illegal("k6r/8/8/8/7R/8/3P4/3R3K w - - 0 1", "Rd4"), None);
+    }
+}
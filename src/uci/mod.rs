@@ -0,0 +1,126 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::bit_board::BitBoardPosition;
+use crate::definitions::{Move, Piece, PieceType, Player, Square};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum UciError {
+    InvalidSyntax(String),
+    IllegalMove(String),
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSyntax(uci) => write!(f, "\"{}\" isn't a move in coordinate notation like e2e4 or e7e8q", uci),
+            Self::IllegalMove(uci) => write!(f, "{} isn't a legal move here", uci),
+        }
+    }
+}
+
+impl std::error::Error for UciError {}
+
+/// Splits coordinate notation into the squares and promotion piece
+/// `BitBoardPosition::make_move` takes, without needing a position.
+pub fn parse_uci(uci: &str) -> Result<(Square, Square, Option<PieceType>), UciError> {
+    let invalid = || UciError::InvalidSyntax(uci.to_string());
+
+    if !uci.is_ascii() || !(4..=5).contains(&uci.len()) {
+        return Err(invalid());
+    }
+
+    // coordinate notation is lowercase only
+    let square = |name: &str| match name.chars().next() {
+        Some('a'..='h') => Square::from_str(name).map_err(|_| invalid()),
+        _ => Err(invalid()),
+    };
+    let start = square(&uci[0..2])?;
+    let end = square(&uci[2..4])?;
+
+    let promotion = match uci[4..].chars().next() {
+        None => None,
+        Some(symbol @ ('q' | 'r' | 'b' | 'n')) => Some(Piece::from_fen_char(symbol).unwrap().piece_type),
+        Some(_) => return Err(invalid()),
+    };
+
+    Ok((start, end, promotion))
+}
+
+impl Move {
+    /// Resolves coordinate notation, `e2e4`, `e7e8q` or `e1g1` for castling,
+    /// to one of the legal moves in `position`.
+    pub fn from_uci(uci: &str, position: &BitBoardPosition) -> Result<Self, UciError> {
+        let (start, end, promotion) = parse_uci(uci)?;
+        position.legal_moves()
+            .into_iter()
+            .find(|legal_move| legal_move.start == start && legal_move.end == end && legal_move.promotion == promotion)
+            .ok_or_else(|| UciError::IllegalMove(uci.to_string()))
+    }
+
+    /// The start and end squares in lowercase, followed by the promotion
+    /// piece if there is one. Castling is written as the king's move.
+    pub fn to_uci(self) -> String {
+        let mut uci = format!("{}{}", self.start, self.end);
+        if let Some(promotion) = self.promotion {
+            uci.push(Piece { piece_type: promotion, owner: Player::Black }.fen_char());
+        }
+        uci
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_uci, UciError};
+    use crate::bit_board::BitBoardPosition;
+    use crate::definitions::{Move, PieceType, Square};
+
+    fn position(fen: &str) -> BitBoardPosition {
+        BitBoardPosition::from_fen(fen).unwrap()
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(parse_uci("e2e4"), Ok((Square::E2, Square::E4, None)));
+        assert_eq!(parse_uci("e7e8q"), Ok((Square::E7, Square::E8, Some(PieceType::Queen))));
+        assert_eq!(parse_uci("a2a1n"), Ok((Square::A2, Square::A1, Some(PieceType::Knight))));
+
+        for invalid in ["", "e2", "e2e", "e2e4qq", "E2E4", "e2E4", "e7e8Q", "e7e8k", "i2i4", "e0e9", "e2-e4", "é2e4"] {
+            assert_eq!(parse_uci(invalid), Err(UciError::InvalidSyntax(invalid.to_string())), "{:?}", invalid);
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let promotion = position("7k/4P3/8/8/8/8/8/K7 w - - 0 1");
+        let queen = Move::from_uci("e7e8q", &promotion).unwrap();
+        assert_eq!((queen.start, queen.end, queen.promotion), (Square::E7, Square::E8, Some(PieceType::Queen)));
+        assert_eq!(queen.to_uci(), "e7e8q");
+        assert_eq!(Move::from_uci("e7e8n", &promotion).unwrap().to_uci(), "e7e8n");
+
+        let castling = position("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+        let king_side = Move::from_uci("e1g1", &castling).unwrap();
+        assert!(king_side.is_castle_attempt());
+        assert_eq!(king_side.to_uci(), "e1g1");
+        assert_eq!(Move::from_uci("e1c1", &castling).unwrap().to_uci(), "e1c1");
+
+        let kiwipete = position("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+        for legal_move in kiwipete.legal_moves() {
+            assert_eq!(Move::from_uci(&legal_move.to_uci(), &kiwipete), Ok(legal_move));
+        }
+    }
+
+    #[test]
+    fn illegal_moves() {
+        let initial = position("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+        for illegal in ["e2e5", "e7e5", "e1g1", "a1a3"] {
+            assert_eq!(Move::from_uci(illegal, &initial), Err(UciError::IllegalMove(illegal.to_string())));
+        }
+
+        // a promotion has to name the piece, and only a promotion can
+        let promotion = position("7k/4P3/8/8/8/8/8/K7 w - - 0 1");
+        assert_eq!(Move::from_uci("e7e8", &promotion), Err(UciError::IllegalMove("e7e8".to_string())));
+        assert_eq!(Move::from_uci("a1a2q", &promotion), Err(UciError::IllegalMove("a1a2q".to_string())));
+        assert_eq!(Move::from_uci("e1g1", &position("r3k2r/8/8/8/8/8/8/R3K2R w - - 0 1")), Err(UciError::IllegalMove("e1g1".to_string())));
+    }
+}